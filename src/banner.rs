@@ -0,0 +1,222 @@
+//! Banner grab for services that speak first (SSH, SMTP, FTP, POP3, IMAP, ...).
+
+use crate::probe::ProbeOutcome;
+use regex_lite::Regex;
+use socket2::Socket;
+use std::{
+    fmt::Write as _,
+    io::{self, Read},
+    time::{Duration, Instant},
+};
+
+/// Default number of banner bytes to read.
+pub(crate) const DEFAULT_MAX_BYTES: usize = 256;
+
+/// Pattern a banner must match; compared by its source text.
+#[derive(Clone, Debug)]
+pub(crate) struct BannerPattern(Regex);
+
+impl BannerPattern {
+    pub(crate) fn new(pattern: &str) -> Result<Self, regex_lite::Error> {
+        Regex::new(pattern).map(Self)
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq for BannerPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for BannerPattern {}
+
+/// What to read after connect.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BannerConfig {
+    pub(crate) max_bytes: usize,
+    /// Read timeout; the probe timeout when unset.
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) expect: Option<BannerPattern>,
+}
+
+impl Default for BannerConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+            timeout: None,
+            expect: None,
+        }
+    }
+}
+
+/// Result of a banner read.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Banner {
+    /// Time from connect to the first banner byte.
+    pub(crate) ttfb_ms: Option<f64>,
+    /// Sanitised banner text; `None` if the server sent nothing.
+    pub(crate) text: Option<String>,
+    /// Set when the server closed or reset the connection before the banner
+    /// was complete, with the time until that happened.
+    pub(crate) closed: Option<(ProbeOutcome, f64)>,
+}
+
+impl Banner {
+    /// Outcome of a connected probe after its banner read.
+    pub(crate) fn outcome(&self, config: &BannerConfig) -> ProbeOutcome {
+        if let Some((outcome, _)) = self.closed
+            && self.text.is_none()
+        {
+            return outcome;
+        }
+
+        match (&config.expect, &self.text) {
+            (Some(pattern), Some(text)) if pattern.0.is_match(text) => ProbeOutcome::Open,
+            (Some(_), _) => ProbeOutcome::BannerMismatch,
+            (None, _) => ProbeOutcome::Open,
+        }
+    }
+}
+
+/// Read up to `max_bytes` or one line from a freshly connected socket.
+pub(crate) fn read(socket: &Socket, max_bytes: usize, timeout: Duration) -> Banner {
+    let start = Instant::now();
+    let elapsed_ms = || start.elapsed().as_secs_f64() * 1_000.0;
+    let mut reader = socket;
+    let mut data = Vec::with_capacity(max_bytes);
+    let mut buf = [0; 512];
+    let mut banner = Banner::default();
+
+    while data.len() < max_bytes && !data.contains(&b'\n') {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() || socket.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+
+        let want = buf.len().min(max_bytes - data.len());
+        match reader.read(&mut buf[..want]) {
+            Ok(0) => {
+                banner.closed = Some((ProbeOutcome::OpenThenClosed, elapsed_ms()));
+                break;
+            }
+            Ok(n) => {
+                banner.ttfb_ms.get_or_insert_with(elapsed_ms);
+                data.extend_from_slice(&buf[..n]);
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted
+                ) =>
+            {
+                banner.closed = Some((ProbeOutcome::OpenThenReset, elapsed_ms()));
+                break;
+            }
+            Err(_) => break,
+        }
+    }
+
+    if let Some(end) = data.iter().position(|&byte| byte == b'\n') {
+        data.truncate(end + 1);
+    }
+    banner.text = (!data.is_empty()).then(|| sanitise(&data));
+    banner
+}
+
+/// Printable form of raw banner bytes: the trailing line break is dropped,
+/// other control characters and invalid UTF-8 are escaped.
+pub(crate) fn sanitise(bytes: &[u8]) -> String {
+    let bytes = bytes.trim_ascii_end();
+    let mut out = String::with_capacity(bytes.len());
+
+    for chunk in bytes.utf8_chunks() {
+        for ch in chunk.valid().chars() {
+            match ch {
+                '\r' => out.push_str("\\r"),
+                '\n' => out.push_str("\\n"),
+                '\t' => out.push_str("\\t"),
+                '\\' => out.push_str("\\\\"),
+                ch if ch.is_control() => {
+                    let _ = write!(out, "\\u{{{:x}}}", u32::from(ch));
+                }
+                ch => out.push(ch),
+            }
+        }
+        for byte in chunk.invalid() {
+            let _ = write!(out, "\\x{byte:02x}");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitise_escapes_control_bytes() {
+        assert_eq!(sanitise(b"SSH-2.0-OpenSSH_9.6\r\n"), "SSH-2.0-OpenSSH_9.6");
+        assert_eq!(sanitise(b"a\tb\x1b[0m\xff"), "a\\tb\\u{1b}[0m\\xff");
+        assert_eq!(
+            sanitise(b"220 mail ESMTP\r\n250 more"),
+            "220 mail ESMTP\\r\\n250 more"
+        );
+    }
+
+    #[test]
+    fn outcome_checks_the_expected_pattern() {
+        let config = BannerConfig {
+            expect: Some(BannerPattern::new("^SSH-2\\.0-").unwrap()),
+            ..BannerConfig::default()
+        };
+        let banner = |text: Option<&str>| Banner {
+            text: text.map(str::to_string),
+            ..Banner::default()
+        };
+
+        assert_eq!(
+            banner(Some("SSH-2.0-OpenSSH_9.6")).outcome(&config),
+            ProbeOutcome::Open
+        );
+        assert_eq!(
+            banner(Some("220 smtp ready")).outcome(&config),
+            ProbeOutcome::BannerMismatch
+        );
+        assert_eq!(banner(None).outcome(&config), ProbeOutcome::BannerMismatch);
+        assert_eq!(
+            banner(None).outcome(&BannerConfig::default()),
+            ProbeOutcome::Open
+        );
+
+        let closed = Banner {
+            closed: Some((ProbeOutcome::OpenThenClosed, 3.0)),
+            ..Banner::default()
+        };
+        assert_eq!(closed.outcome(&config), ProbeOutcome::OpenThenClosed);
+    }
+
+    #[test]
+    fn read_stops_at_the_first_line() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            std::io::Write::write_all(&mut stream, b"220 ready\r\nEHLO more\r\n").unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+        });
+
+        let stream = std::net::TcpStream::connect(addr).unwrap();
+        let socket = Socket::from(stream);
+        let banner = read(&socket, DEFAULT_MAX_BYTES, Duration::from_secs(1));
+        server.join().unwrap();
+
+        assert_eq!(banner.text.as_deref(), Some("220 ready"));
+        assert!(banner.ttfb_ms.is_some());
+        assert_eq!(banner.closed, None);
+    }
+}
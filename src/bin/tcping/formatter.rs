@@ -3,7 +3,11 @@
 use crate::cli::{OutputMode, TimestampFormat};
 use serde::Serialize;
 use serde_json::to_string;
-use tcping::{PingResult, RecordTimestamp, ResolvedTarget, Summary};
+use tcping::{
+    CertInfo, CloseMode, DnsInfo, GrpcInfo, HttpInfo, PingResult, ProbeOutcome, ProtocolInfo,
+    ProxyInfo, RecordTimestamp, ResolvedTarget, SocketOptions, SourcePortStats, StepResult,
+    Summary, TcpInfo, TcpInfoStats, TlsInfo,
+};
 
 /// Print behaviour contract.
 pub(crate) trait Formatter {
@@ -33,6 +37,236 @@ fn render_timestamp(timestamp: &RecordTimestamp, format: TimestampFormat) -> Str
     }
 }
 
+/// ` (name)` annotation for a probed address with a known PTR name.
+fn ptr_suffix(ptr_name: Option<&str>) -> String {
+    ptr_name
+        .map(|name| format!(" ({name})"))
+        .unwrap_or_default()
+}
+
+/// Trailing notes for a probe line: the banner, when the server closed a held
+/// connection, and a ` [SYN retransmitted]` marker.
+fn probe_notes(res: &PingResult) -> String {
+    let mut notes = String::new();
+    if let Some(proxy) = &res.proxy {
+        notes.push_str(&proxy_note(proxy));
+    }
+    if let Some(tls) = &res.tls {
+        notes.push_str(&tls_note(res.outcome, tls));
+    }
+    if let Some(http) = &res.http {
+        notes.push_str(&http_note(http));
+    }
+    if let Some(grpc) = &res.grpc {
+        notes.push_str(&grpc_note(grpc));
+    }
+    if let Some(protocol) = &res.protocol {
+        notes.push_str(&protocol_note(protocol));
+    }
+    if let Some(dns) = &res.dns {
+        notes.push_str(&dns_note(dns));
+    }
+    if let Some(ttfb) = res.time_to_first_byte_ms {
+        notes.push_str(&format!(", first byte after {ttfb:.4} ms"));
+    }
+    if let Some(banner) = &res.banner {
+        notes.push_str(&format!(", banner \"{banner}\""));
+    }
+    if !res.steps.is_empty() {
+        let steps: Vec<String> = res.steps.iter().map(step_note).collect();
+        notes.push_str(&format!(", steps: {}", steps.join(", ")));
+    }
+    if let Some(ms) = res.time_to_close_ms {
+        notes.push_str(&format!(", closed by server after {ms:.4} ms"));
+    }
+    if res.retransmitted {
+        notes.push_str(" [SYN retransmitted]");
+    }
+    notes
+}
+
+/// `, socks5h tunnel to example.com:443 in 12.3000 ms`, or the failure.
+fn proxy_note(proxy: &ProxyInfo) -> String {
+    let kind = proxy.kind;
+    match &proxy.error {
+        Some(error) => format!(
+            ", {kind} proxy failed after {:.4} ms ({error})",
+            proxy.tunnel_ms
+        ),
+        None => format!(
+            ", {kind} tunnel to {} in {:.4} ms",
+            proxy.target, proxy.tunnel_ms
+        ),
+    }
+}
+
+/// `, STARTTLS 0.4000 ms, TLSv1.3 TLS13_AES_128_GCM_SHA256 alpn=h2 handshake 3.1000 ms`.
+fn tls_note(outcome: ProbeOutcome, tls: &TlsInfo) -> String {
+    let starttls_ms = tls.starttls_ms.unwrap_or_default();
+    if let Some(error) = &tls.error {
+        return if outcome == ProbeOutcome::StartTlsFailed {
+            format!(", STARTTLS failed after {starttls_ms:.4} ms ({error})")
+        } else if tls.starttls_ms.is_some() {
+            format!(
+                ", STARTTLS {starttls_ms:.4} ms, TLS failed after {:.4} ms ({error})",
+                tls.handshake_ms
+            )
+        } else {
+            format!(", TLS failed after {:.4} ms ({error})", tls.handshake_ms)
+        };
+    }
+
+    let mut note = match tls.starttls_ms {
+        Some(ms) => format!(", STARTTLS {ms:.4} ms,"),
+        None => String::from(","),
+    };
+    for part in [&tls.version, &tls.cipher].into_iter().flatten() {
+        note.push(' ');
+        note.push_str(part);
+    }
+    if let Some(alpn) = &tls.alpn {
+        note.push_str(&format!(" alpn={alpn}"));
+    }
+    note.push_str(&format!(" handshake {:.4} ms", tls.handshake_ms));
+    note
+}
+
+/// `, HTTP 200 first byte 1.2000 ms total 1.5000 ms`, or the failure.
+fn http_note(http: &HttpInfo) -> String {
+    match (&http.error, http.status) {
+        (Some(error), _) => format!(", HTTP failed after {:.4} ms ({error})", http.total_ms),
+        (None, Some(status)) => format!(
+            ", HTTP {status} first byte {:.4} ms total {:.4} ms",
+            http.ttfb_ms.unwrap_or_default(),
+            http.total_ms
+        ),
+        (None, None) => String::new(),
+    }
+}
+
+/// `, gRPC SERVING in 2.1000 ms`, or the failure.
+fn grpc_note(grpc: &GrpcInfo) -> String {
+    match (&grpc.error, &grpc.serving_status) {
+        (Some(error), _) => format!(", gRPC failed after {:.4} ms ({error})", grpc.rpc_ms),
+        (None, Some(status)) => format!(", gRPC {status} in {:.4} ms", grpc.rpc_ms),
+        (None, None) => String::new(),
+    }
+}
+
+/// `, memcached 1.6.21 reply 0.2100 ms`, or the failure.
+fn protocol_note(protocol: &ProtocolInfo) -> String {
+    let name = protocol.protocol;
+    match (&protocol.error, &protocol.server_version) {
+        (Some(error), _) => format!(", {name} failed after {:.4} ms ({error})", protocol.rtt_ms),
+        (None, Some(version)) => format!(", {name} {version} reply {:.4} ms", protocol.rtt_ms),
+        (None, None) => format!(", {name} reply {:.4} ms", protocol.rtt_ms),
+    }
+}
+
+/// `, DNS NOERROR 2 answers in 0.9000 ms`, or the failure.
+fn dns_note(dns: &DnsInfo) -> String {
+    match (&dns.error, &dns.rcode) {
+        (Some(error), _) => format!(", DNS failed after {:.4} ms ({error})", dns.query_ms),
+        (None, Some(rcode)) => format!(
+            ", DNS {rcode} {} answer{} in {:.4} ms",
+            dns.answers,
+            if dns.answers == 1 { "" } else { "s" },
+            dns.query_ms
+        ),
+        (None, None) => String::new(),
+    }
+}
+
+/// `expect 0.2100 ms`, or `expect failed after 500.0000 ms (timed out, got "-ERR")`.
+fn step_note(step: &StepResult) -> String {
+    if step.success {
+        return format!("{} {:.4} ms", step.action, step.duration_ms);
+    }
+
+    let got = step
+        .received
+        .as_ref()
+        .map(|received| format!(", got \"{received}\""))
+        .unwrap_or_default();
+    format!(
+        "{} failed after {:.4} ms ({}{got})",
+        step.action,
+        step.duration_ms,
+        step.error.as_deref().unwrap_or("failed")
+    )
+}
+
+/// Per-source-port breakdown lines for a sweep summary.
+fn source_port_lines(s: &Summary) -> Vec<String> {
+    if s.source_ports.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines = vec!["Per source port:".to_string()];
+    lines.extend(s.source_ports.iter().map(|port| {
+        format!(
+            "  {}: {} sent, {} successful, {:.2}% loss, min/avg/max = {:.4}/{:.4}/{:.4} ms",
+            port.source_port,
+            port.total_attempts,
+            port.successful_pings,
+            port.packet_loss,
+            port.min_duration_ms,
+            port.avg_duration_ms,
+            port.max_duration_ms
+        )
+    }));
+    lines
+}
+
+/// Note on how connections were closed, for anything but the default FIN.
+fn close_mode_line(s: &Summary) -> Option<&'static str> {
+    match s.close_mode {
+        CloseMode::Fin => None,
+        CloseMode::Rst => Some("Connections closed with RST (SO_LINGER=0)"),
+        CloseMode::HalfClose => Some("Connections half-closed and drained until the server's FIN"),
+        _ => None,
+    }
+}
+
+/// Leaf certificate details and expiry warnings for a TLS session summary.
+fn certificate_lines(s: &Summary) -> Vec<String> {
+    let Some(cert) = &s.certificate else {
+        return Vec::new();
+    };
+
+    let mut lines = vec![
+        format!("Certificate: {} (issuer {})", cert.subject, cert.issuer),
+        format!(
+            "Certificate expires {} ({} days), chain length {}, SANs: {}",
+            cert.not_after,
+            cert.days_remaining,
+            cert.chain_len,
+            cert.sans.join(", ")
+        ),
+    ];
+    if !cert.name_matches {
+        lines.push("WARNING: certificate does not cover the target name".into());
+    }
+    if let Some(days) = s.cert_warn_days.filter(|_| s.cert_expiring()) {
+        lines.push(format!("WARNING: certificate expires within {days} days"));
+    }
+    lines
+}
+
+/// Kernel RTT line for a summary with `TCP_INFO` samples.
+fn kernel_rtt_line(s: &Summary) -> Option<String> {
+    s.tcp_info.as_ref().map(|kernel| {
+        format!(
+            "Kernel RTT min/avg/max = {:.3}/{:.3}/{:.3} ms, rttvar avg = {:.3} ms, retransmits = {}",
+            kernel.min_rtt_ms,
+            kernel.avg_rtt_ms,
+            kernel.max_rtt_ms,
+            kernel.avg_rttvar_ms,
+            kernel.total_retransmits
+        )
+    })
+}
+
 struct Normal {
     timestamp_format: Option<TimestampFormat>,
 }
@@ -44,14 +278,16 @@ impl Normal {
 
     fn render_probe(&self, res: &PingResult) -> String {
         let prefix = human_timestamp(res.timestamp.as_ref(), self.timestamp_format);
-        let status = if res.success { "open" } else { "closed" };
+        let ptr = ptr_suffix(res.ptr_name.as_deref());
+        let retx = probe_notes(res);
+        let status = res.outcome.as_str();
         match res.jitter_ms {
             Some(j) => format!(
-                "{prefix}Probing {}/tcp - {status} - {:.4} ms jitter={:.4} ms",
+                "{prefix}Probing {}/tcp{ptr} - {status} - {:.4} ms jitter={:.4} ms{retx}",
                 res.addr, res.duration_ms, j
             ),
             None => format!(
-                "{prefix}Probing {}/tcp - {status} - {:.4} ms",
+                "{prefix}Probing {}/tcp{ptr} - {status} - {:.4} ms{retx}",
                 res.addr, res.duration_ms
             ),
         }
@@ -71,7 +307,7 @@ impl Formatter for Normal {
             .unwrap_or_else(|| "system default".into());
         println!(
             "\nResolved {} -> {}  (DNS {dns})  in {:.4} ms\n",
-            resolved.target.host(),
+            resolved.target.unicode_host(),
             resolved.addr.ip(),
             resolved.resolve_time_ms
         );
@@ -83,8 +319,9 @@ impl Formatter for Normal {
 
     fn summary(&mut self, s: &Summary) {
         let prefix = human_timestamp(s.timestamp.as_ref(), self.timestamp_format);
+        let ptr = ptr_suffix(s.ptr_name.as_deref());
         println!(
-            "\n{prefix}--- {} tcping statistics ---
+            "\n{prefix}--- {}{ptr} tcping statistics ---
 {} probes sent, {} successful, {:.2}% packet loss",
             s.addr, s.total_attempts, s.successful_pings, s.packet_loss
         );
@@ -97,6 +334,18 @@ impl Formatter for Normal {
         if let Some(j95) = s.jitter_p95_ms {
             println!("Jitter p95 = {:.4} ms", j95);
         }
+        if let Some(line) = kernel_rtt_line(s) {
+            println!("{line}");
+        }
+        if let Some(line) = close_mode_line(s) {
+            println!("{line}");
+        }
+        for line in certificate_lines(s) {
+            println!("{line}");
+        }
+        for line in source_port_lines(s) {
+            println!("{line}");
+        }
     }
 }
 
@@ -124,9 +373,251 @@ struct JsonProbe {
     #[serde(skip_serializing_if = "Option::is_none")]
     timestamp_unix_ms: Option<i64>,
     success: bool,
+    outcome: &'static str,
     duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_to_close_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_to_first_byte_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    banner: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    steps: Vec<JsonStep>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tls: Option<JsonTls>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http: Option<JsonHttp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grpc: Option<JsonGrpc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protocol: Option<JsonProtocol>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dns: Option<JsonDns>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxy: Option<JsonProxy>,
     jitter_ms: Option<f64>,
     addr: std::net::SocketAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_addr: Option<std::net::SocketAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ptr_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    socket_options: Option<JsonSocketOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tcp_info: Option<JsonTcpInfo>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    retransmitted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    close_mode: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct JsonTls {
+    handshake_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    starttls_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cipher: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alpn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<&TlsInfo> for JsonTls {
+    fn from(tls: &TlsInfo) -> Self {
+        Self {
+            handshake_ms: round4(tls.handshake_ms),
+            starttls_ms: tls.starttls_ms.map(round4),
+            version: tls.version.clone(),
+            cipher: tls.cipher.clone(),
+            alpn: tls.alpn.clone(),
+            error: tls.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonHttp {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttfb_ms: Option<f64>,
+    total_ms: f64,
+    body_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<&HttpInfo> for JsonHttp {
+    fn from(http: &HttpInfo) -> Self {
+        Self {
+            status: http.status,
+            ttfb_ms: http.ttfb_ms.map(round4),
+            total_ms: round4(http.total_ms),
+            body_bytes: http.body_bytes,
+            error: http.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonGrpc {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    serving_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grpc_status: Option<u32>,
+    rpc_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<&GrpcInfo> for JsonGrpc {
+    fn from(grpc: &GrpcInfo) -> Self {
+        Self {
+            serving_status: grpc.serving_status.clone(),
+            grpc_status: grpc.grpc_status,
+            rpc_ms: round4(grpc.rpc_ms),
+            error: grpc.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonProtocol {
+    name: &'static str,
+    rtt_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<&ProtocolInfo> for JsonProtocol {
+    fn from(protocol: &ProtocolInfo) -> Self {
+        Self {
+            name: protocol.protocol.as_str(),
+            rtt_ms: round4(protocol.rtt_ms),
+            server_version: protocol.server_version.clone(),
+            error: protocol.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonDns {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rcode: Option<String>,
+    answers: usize,
+    query_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<&DnsInfo> for JsonDns {
+    fn from(dns: &DnsInfo) -> Self {
+        Self {
+            rcode: dns.rcode.clone(),
+            answers: dns.answers,
+            query_ms: round4(dns.query_ms),
+            error: dns.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonProxy {
+    kind: &'static str,
+    target: String,
+    tunnel_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<&ProxyInfo> for JsonProxy {
+    fn from(proxy: &ProxyInfo) -> Self {
+        Self {
+            kind: proxy.kind.as_str(),
+            target: proxy.target.clone(),
+            tunnel_ms: round4(proxy.tunnel_ms),
+            error: proxy.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonStep {
+    action: &'static str,
+    success: bool,
+    duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    received: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<&StepResult> for JsonStep {
+    fn from(step: &StepResult) -> Self {
+        Self {
+            action: step.action,
+            success: step.success,
+            duration_ms: round4(step.duration_ms),
+            received: step.received.clone(),
+            error: step.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonTcpInfo {
+    rtt_ms: f64,
+    rttvar_ms: f64,
+    snd_mss: u32,
+    rcv_mss: u32,
+    retransmits: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snd_wscale: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rcv_wscale: Option<u8>,
+}
+
+impl From<&TcpInfo> for JsonTcpInfo {
+    fn from(info: &TcpInfo) -> Self {
+        Self {
+            rtt_ms: round4(info.rtt_ms),
+            rttvar_ms: round4(info.rttvar_ms),
+            snd_mss: info.snd_mss,
+            rcv_mss: info.rcv_mss,
+            retransmits: info.retransmits,
+            snd_wscale: info.snd_wscale,
+            rcv_wscale: info.rcv_wscale,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonSocketOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tos: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dscp: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mark: Option<u32>,
+}
+
+impl From<&SocketOptions> for JsonSocketOptions {
+    fn from(options: &SocketOptions) -> Self {
+        Self {
+            ttl: options.ttl,
+            tos: options.tos,
+            dscp: options.dscp(),
+            mark: options.mark,
+        }
+    }
 }
 
 impl From<&PingResult> for JsonProbe {
@@ -137,9 +628,26 @@ impl From<&PingResult> for JsonProbe {
             timestamp: res.timestamp.as_ref().map(|ts| ts.rfc3339().to_string()),
             timestamp_unix_ms: res.timestamp.as_ref().map(RecordTimestamp::unix_ms),
             success: res.success,
+            outcome: res.outcome.as_str(),
             duration_ms: round4(res.duration_ms),
+            time_to_close_ms: res.time_to_close_ms.map(round4),
+            time_to_first_byte_ms: res.time_to_first_byte_ms.map(round4),
+            banner: res.banner.clone(),
+            steps: res.steps.iter().map(JsonStep::from).collect(),
+            tls: res.tls.as_ref().map(JsonTls::from),
+            http: res.http.as_ref().map(JsonHttp::from),
+            grpc: res.grpc.as_ref().map(JsonGrpc::from),
+            protocol: res.protocol.as_ref().map(JsonProtocol::from),
+            dns: res.dns.as_ref().map(JsonDns::from),
+            proxy: res.proxy.as_ref().map(JsonProxy::from),
             jitter_ms: res.jitter_ms.map(round4),
             addr: res.addr,
+            local_addr: res.local_addr,
+            ptr_name: res.ptr_name.clone(),
+            socket_options: res.socket_options.as_ref().map(JsonSocketOptions::from),
+            tcp_info: res.tcp_info.as_ref().map(JsonTcpInfo::from),
+            retransmitted: res.retransmitted,
+            close_mode: res.close_mode.as_ref().map(CloseMode::as_str),
         }
     }
 }
@@ -153,6 +661,8 @@ struct JsonSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
     timestamp_unix_ms: Option<i64>,
     addr: std::net::SocketAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ptr_name: Option<String>,
     total_attempts: usize,
     successful_pings: usize,
     packet_loss: f64,
@@ -161,6 +671,90 @@ struct JsonSummary {
     max_duration_ms: f64,
     resolve_time_ms: f64,
     jitter_p95_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    source_ports: Vec<JsonSourcePort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tcp_info: Option<JsonTcpInfoStats>,
+    close_mode: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    certificate: Option<JsonCertificate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_warn_days: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cert_expiring: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct JsonCertificate {
+    subject: String,
+    issuer: String,
+    sans: Vec<String>,
+    not_after: String,
+    days_remaining: i64,
+    chain_len: usize,
+    name_matches: bool,
+}
+
+impl From<&CertInfo> for JsonCertificate {
+    fn from(cert: &CertInfo) -> Self {
+        Self {
+            subject: cert.subject.clone(),
+            issuer: cert.issuer.clone(),
+            sans: cert.sans.clone(),
+            not_after: cert.not_after.clone(),
+            days_remaining: cert.days_remaining,
+            chain_len: cert.chain_len,
+            name_matches: cert.name_matches,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonTcpInfoStats {
+    samples: usize,
+    min_rtt_ms: f64,
+    avg_rtt_ms: f64,
+    max_rtt_ms: f64,
+    avg_rttvar_ms: f64,
+    total_retransmits: u64,
+}
+
+impl From<&TcpInfoStats> for JsonTcpInfoStats {
+    fn from(kernel: &TcpInfoStats) -> Self {
+        Self {
+            samples: kernel.samples,
+            min_rtt_ms: round4(kernel.min_rtt_ms),
+            avg_rtt_ms: round4(kernel.avg_rtt_ms),
+            max_rtt_ms: round4(kernel.max_rtt_ms),
+            avg_rttvar_ms: round4(kernel.avg_rttvar_ms),
+            total_retransmits: kernel.total_retransmits,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonSourcePort {
+    source_port: u16,
+    total_attempts: usize,
+    successful_pings: usize,
+    packet_loss: f64,
+    min_duration_ms: f64,
+    avg_duration_ms: f64,
+    max_duration_ms: f64,
+}
+
+impl From<&SourcePortStats> for JsonSourcePort {
+    fn from(port: &SourcePortStats) -> Self {
+        Self {
+            source_port: port.source_port,
+            total_attempts: port.total_attempts,
+            successful_pings: port.successful_pings,
+            packet_loss: round2(port.packet_loss),
+            min_duration_ms: round4(port.min_duration_ms),
+            avg_duration_ms: round4(port.avg_duration_ms),
+            max_duration_ms: round4(port.max_duration_ms),
+        }
+    }
 }
 
 impl From<&Summary> for JsonSummary {
@@ -171,6 +765,7 @@ impl From<&Summary> for JsonSummary {
             timestamp: s.timestamp.as_ref().map(|ts| ts.rfc3339().to_string()),
             timestamp_unix_ms: s.timestamp.as_ref().map(RecordTimestamp::unix_ms),
             addr: s.addr,
+            ptr_name: s.ptr_name.clone(),
             total_attempts: s.total_attempts,
             successful_pings: s.successful_pings,
             packet_loss: round2(s.packet_loss),
@@ -179,6 +774,12 @@ impl From<&Summary> for JsonSummary {
             max_duration_ms: round4(s.max_duration_ms),
             resolve_time_ms: round4(s.resolve_time_ms),
             jitter_p95_ms: s.jitter_p95_ms.map(round4),
+            source_ports: s.source_ports.iter().map(JsonSourcePort::from).collect(),
+            tcp_info: s.tcp_info.as_ref().map(JsonTcpInfoStats::from),
+            close_mode: s.close_mode.as_str(),
+            certificate: s.certificate.as_ref().map(JsonCertificate::from),
+            cert_warn_days: s.cert_warn_days,
+            cert_expiring: s.cert_warn_days.map(|_| s.cert_expiring()),
         }
     }
 }
@@ -205,36 +806,61 @@ const CSV_COLUMNS_V2: usize = 16;
 struct Csv {
     header_done: bool,
     timestamps_enabled: bool,
+    ptr_names: bool,
 }
 
 impl Csv {
-    fn new(timestamps_enabled: bool) -> Self {
+    fn new(timestamps_enabled: bool, ptr_names: bool) -> Self {
         Self {
             header_done: false,
             timestamps_enabled,
+            ptr_names,
+        }
+    }
+
+    fn header(&self) -> String {
+        let header = if self.timestamps_enabled {
+            CSV_HEADER_V2
+        } else {
+            CSV_HEADER_V1
+        };
+        if self.ptr_names {
+            header.replace(",schema", ",ptr_name,schema")
+        } else {
+            header.to_string()
         }
     }
 
     fn ensure_header(&mut self) {
         if !self.header_done {
             self.header_done = true;
-            println!(
-                "{}",
-                if self.timestamps_enabled {
-                    CSV_HEADER_V2
-                } else {
-                    CSV_HEADER_V1
-                }
-            );
+            println!("{}", self.header());
         }
     }
 
+    /// Insert optional columns ahead of the trailing `schema` column.
+    fn finish_row(&self, mut fields: Vec<String>, ptr_name: Option<&str>) -> String {
+        if self.ptr_names {
+            let schema = fields.len() - 1;
+            fields.insert(schema, csv_escape(ptr_name.unwrap_or_default()));
+        }
+        fields.join(",")
+    }
+
+    fn probe_row(&self, res: &PingResult) -> String {
+        self.finish_row(Self::probe_fields(res), res.ptr_name.as_deref())
+    }
+
+    fn summary_row(&self, s: &Summary) -> String {
+        self.finish_row(Self::summary_fields(s), s.ptr_name.as_deref())
+    }
+
     fn fmt_opt_ms(v: Option<f64>) -> String {
         v.map(|x| format!("{:.4}", x)).unwrap_or_default()
     }
 
-    fn probe_row(res: &PingResult) -> String {
-        let status = if res.success { "open" } else { "closed" };
+    fn probe_fields(res: &PingResult) -> Vec<String> {
+        let status = res.outcome.as_str();
 
         if let Some(timestamp) = res.timestamp.as_ref() {
             let mut fields = vec![String::new(); CSV_COLUMNS_V2];
@@ -246,7 +872,7 @@ impl Csv {
             fields[5] = format!("{:.4}", res.duration_ms);
             fields[6] = Self::fmt_opt_ms(res.jitter_ms);
             fields[15] = res.schema.to_string();
-            fields.join(",")
+            fields
         } else {
             let mut fields = vec![String::new(); CSV_COLUMNS_V1];
             fields[0] = res.record.to_string();
@@ -255,11 +881,11 @@ impl Csv {
             fields[3] = format!("{:.4}", res.duration_ms);
             fields[4] = Self::fmt_opt_ms(res.jitter_ms);
             fields[13] = res.schema.to_string();
-            fields.join(",")
+            fields
         }
     }
 
-    fn summary_row(s: &Summary) -> String {
+    fn summary_fields(s: &Summary) -> Vec<String> {
         if let Some(timestamp) = s.timestamp.as_ref() {
             let mut fields = vec![String::new(); CSV_COLUMNS_V2];
             fields[0] = s.record.to_string();
@@ -275,7 +901,7 @@ impl Csv {
             fields[13] = format!("{:.4}", s.resolve_time_ms);
             fields[14] = Self::fmt_opt_ms(s.jitter_p95_ms);
             fields[15] = s.schema.to_string();
-            fields.join(",")
+            fields
         } else {
             let mut fields = vec![String::new(); CSV_COLUMNS_V1];
             fields[0] = s.record.to_string();
@@ -289,25 +915,34 @@ impl Csv {
             fields[11] = format!("{:.4}", s.resolve_time_ms);
             fields[12] = Self::fmt_opt_ms(s.jitter_p95_ms);
             fields[13] = s.schema.to_string();
-            fields.join(",")
+            fields
         }
     }
 }
 
 impl Default for Csv {
     fn default() -> Self {
-        Self::new(false)
+        Self::new(false, false)
+    }
+}
+
+/// Quote a free-form CSV value when it contains separators or quotes.
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
     }
 }
 impl Formatter for Csv {
     fn probe(&mut self, res: &PingResult) {
         self.ensure_header();
-        println!("{}", Self::probe_row(res));
+        println!("{}", self.probe_row(res));
     }
 
     fn summary(&mut self, s: &Summary) {
         self.ensure_header();
-        println!("{}", Self::summary_row(s));
+        println!("{}", self.summary_row(s));
     }
 }
 
@@ -392,6 +1027,18 @@ impl Formatter for Md {
         if let Some(j95) = s.jitter_p95_ms {
             println!("| jitter p95 (ms) | {:.4} |", j95);
         }
+        for port in &s.source_ports {
+            println!(
+                "| source port {} | {} sent / {} ok / {:.2}% loss / {:.4} / {:.4} / {:.4} ms |",
+                port.source_port,
+                port.total_attempts,
+                port.successful_pings,
+                port.packet_loss,
+                port.min_duration_ms,
+                port.avg_duration_ms,
+                port.max_duration_ms
+            );
+        }
         println!();
     }
 }
@@ -409,19 +1056,22 @@ impl Color {
 
     fn render_probe(&self, res: &PingResult) -> String {
         let prefix = human_timestamp(res.timestamp.as_ref(), self.timestamp_format);
-        let (status, color) = if res.success {
-            ("open", "\x1b[32m") // green
+        let status = res.outcome.as_str();
+        let color = if res.success {
+            "\x1b[32m" // green
         } else {
-            ("closed", "\x1b[31m") // red
+            "\x1b[31m" // red
         };
         let reset = "\x1b[0m";
+        let ptr = ptr_suffix(res.ptr_name.as_deref());
+        let retx = probe_notes(res);
         match res.jitter_ms {
             Some(j) => format!(
-                "{prefix}Probing {}/tcp - {color}{status}{reset} - {:.4} ms jitter={:.4} ms",
+                "{prefix}Probing {}/tcp{ptr} - {color}{status}{reset} - {:.4} ms jitter={:.4} ms{retx}",
                 res.addr, res.duration_ms, j
             ),
             None => format!(
-                "{prefix}Probing {}/tcp - {color}{status}{reset} - {:.4} ms",
+                "{prefix}Probing {}/tcp{ptr} - {color}{status}{reset} - {:.4} ms{retx}",
                 res.addr, res.duration_ms
             ),
         }
@@ -438,6 +1088,7 @@ impl Formatter for Color {
         let bad_color = "\x1b[31m";
         let reset = "\x1b[0m";
         let prefix = human_timestamp(s.timestamp.as_ref(), self.timestamp_format);
+        let ptr = ptr_suffix(s.ptr_name.as_deref());
 
         let color = if s.packet_loss == 0.0 {
             ok_color
@@ -445,7 +1096,7 @@ impl Formatter for Color {
             bad_color
         };
         println!(
-            "\n{prefix}--- {} tcping statistics ---\n\
+            "\n{prefix}--- {}{ptr} tcping statistics ---\n\
 {} probes sent, {} successful, {color}{:.2}%{reset} packet loss",
             s.addr, s.total_attempts, s.successful_pings, s.packet_loss
         );
@@ -458,6 +1109,18 @@ impl Formatter for Color {
         if let Some(j95) = s.jitter_p95_ms {
             println!("Jitter p95 = {:.4} ms", j95);
         }
+        if let Some(line) = kernel_rtt_line(s) {
+            println!("{line}");
+        }
+        if let Some(line) = close_mode_line(s) {
+            println!("{line}");
+        }
+        for line in certificate_lines(s) {
+            println!("{line}");
+        }
+        for line in source_port_lines(s) {
+            println!("{line}");
+        }
     }
 }
 
@@ -466,11 +1129,12 @@ impl Formatter for Color {
 pub(crate) fn from_mode(
     mode: OutputMode,
     timestamp_format: Option<TimestampFormat>,
+    ptr_names: bool,
 ) -> Box<dyn Formatter> {
     match mode {
         OutputMode::Normal => Box::new(Normal::new(timestamp_format)),
         OutputMode::Json => Box::new(Json),
-        OutputMode::Csv => Box::new(Csv::new(timestamp_format.is_some())),
+        OutputMode::Csv => Box::new(Csv::new(timestamp_format.is_some(), ptr_names)),
         OutputMode::Md => Box::new(Md::new(timestamp_format)),
         OutputMode::Color => Box::new(Color::new(timestamp_format)),
     }
@@ -481,7 +1145,7 @@ mod tests {
     use super::*;
     use crate::cli::TimestampFormat;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-    use tcping::{OUTPUT_SCHEMA_V1, OUTPUT_SCHEMA_V2, RecordTimestamp};
+    use tcping::{OUTPUT_SCHEMA_V1, OUTPUT_SCHEMA_V2, Protocol, ProxyKind, RecordTimestamp};
 
     fn sample_timestamp() -> RecordTimestamp {
         RecordTimestamp::from_unix_ms(1_746_072_812_345)
@@ -493,15 +1157,21 @@ mod tests {
         timestamp: Option<RecordTimestamp>,
         schema: &'static str,
     ) -> PingResult {
-        PingResult {
-            schema,
-            record: "probe",
-            timestamp,
-            success,
-            duration_ms: 42.0,
-            jitter_ms: jitter,
-            addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80),
-        }
+        let outcome = if success {
+            ProbeOutcome::Open
+        } else {
+            ProbeOutcome::Closed
+        };
+        let mut res = PingResult::new(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80),
+            outcome,
+            42.0,
+        );
+        res.schema = schema;
+        res.timestamp = timestamp;
+        res.jitter_ms = jitter;
+        res.close_mode = success.then_some(CloseMode::Fin);
+        res
     }
 
     fn sample_summary(
@@ -509,20 +1179,18 @@ mod tests {
         timestamp: Option<RecordTimestamp>,
         schema: &'static str,
     ) -> Summary {
-        Summary {
-            schema,
-            record: "summary",
-            timestamp,
-            addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80),
-            total_attempts: 4,
-            successful_pings: 3,
-            packet_loss: 25.0,
-            min_duration_ms: 1.0,
-            avg_duration_ms: 2.0,
-            max_duration_ms: 3.0,
-            resolve_time_ms: 0.5,
-            jitter_p95_ms: jitter_p95,
-        }
+        let mut summary = Summary::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80));
+        summary.schema = schema;
+        summary.timestamp = timestamp;
+        summary.total_attempts = 4;
+        summary.successful_pings = 3;
+        summary.packet_loss = 25.0;
+        summary.min_duration_ms = 1.0;
+        summary.avg_duration_ms = 2.0;
+        summary.max_duration_ms = 3.0;
+        summary.resolve_time_ms = 0.5;
+        summary.jitter_p95_ms = jitter_p95;
+        summary
     }
 
     #[test]
@@ -565,11 +1233,12 @@ mod tests {
         assert_eq!(CSV_HEADER_V1.split(',').count(), CSV_COLUMNS_V1);
         assert_eq!(CSV_HEADER_V2.split(',').count(), CSV_COLUMNS_V2);
 
-        let probe_row = Csv::probe_row(&sample_result(true, None, None, OUTPUT_SCHEMA_V1));
+        let probe_row =
+            Csv::default().probe_row(&sample_result(true, None, None, OUTPUT_SCHEMA_V1));
         assert_eq!(probe_row.split(',').count(), CSV_COLUMNS_V1);
         assert_eq!(probe_row.split(',').next_back(), Some(OUTPUT_SCHEMA_V1));
 
-        let probe_row = Csv::probe_row(&sample_result(
+        let probe_row = Csv::default().probe_row(&sample_result(
             true,
             Some(1.5),
             Some(sample_timestamp()),
@@ -578,7 +1247,7 @@ mod tests {
         assert_eq!(probe_row.split(',').count(), CSV_COLUMNS_V2);
         assert_eq!(probe_row.split(',').next_back(), Some(OUTPUT_SCHEMA_V2));
 
-        let summary_row = Csv::summary_row(&sample_summary(
+        let summary_row = Csv::default().summary_row(&sample_summary(
             Some(1.23),
             Some(sample_timestamp()),
             OUTPUT_SCHEMA_V2,
@@ -589,7 +1258,7 @@ mod tests {
 
     #[test]
     fn csv_summary_columns_are_aligned() {
-        let row = Csv::summary_row(&sample_summary(None, None, OUTPUT_SCHEMA_V1));
+        let row = Csv::default().summary_row(&sample_summary(None, None, OUTPUT_SCHEMA_V1));
         let cols: Vec<&str> = row.split(',').collect();
         assert_eq!(cols.len(), CSV_COLUMNS_V1);
         assert_eq!(cols[0], "summary");
@@ -600,6 +1269,31 @@ mod tests {
         assert_eq!(cols[13], OUTPUT_SCHEMA_V1);
     }
 
+    #[test]
+    fn csv_ptr_name_column_precedes_schema() {
+        let csv = Csv::new(false, true);
+        assert!(csv.header().ends_with(",ptr_name,schema"));
+
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.ptr_name = Some("web-1.example.com".into());
+        let row = csv.probe_row(&res);
+        let cols: Vec<&str> = row.split(',').collect();
+        assert_eq!(cols.len(), CSV_COLUMNS_V1 + 1);
+        assert_eq!(cols[CSV_COLUMNS_V1 - 1], "web-1.example.com");
+        assert_eq!(cols[CSV_COLUMNS_V1], OUTPUT_SCHEMA_V1);
+    }
+
+    #[test]
+    fn human_probe_line_shows_ptr_name() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.ptr_name = Some("localhost".into());
+        let line = Normal::new(None).render_probe(&res);
+        assert!(line.contains("127.0.0.1:80/tcp (localhost) - open"));
+
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains("\"ptr_name\":\"localhost\""));
+    }
+
     #[test]
     fn json_includes_timestamp_fields_when_present() {
         let probe = JsonProbe::from(&sample_result(
@@ -613,6 +1307,448 @@ mod tests {
         assert!(json.contains("\"timestamp_unix_ms\":1746072812345"));
     }
 
+    #[test]
+    fn summary_lists_each_source_port_of_a_sweep() {
+        let mut summary = sample_summary(None, None, OUTPUT_SCHEMA_V1);
+        assert!(source_port_lines(&summary).is_empty());
+
+        let mut port = SourcePortStats::new(40_001);
+        port.total_attempts = 4;
+        port.successful_pings = 1;
+        port.packet_loss = 75.0;
+        port.min_duration_ms = 1.0;
+        port.avg_duration_ms = 1.0;
+        port.max_duration_ms = 1.0;
+        summary.source_ports = vec![port];
+        let lines = source_port_lines(&summary);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("  40001: 4 sent, 1 successful, 75.00% loss"));
+
+        let json = to_string(&JsonSummary::from(&summary)).expect("serialize JsonSummary");
+        assert!(json.contains("\"source_ports\":[{\"source_port\":40001"));
+    }
+
+    #[test]
+    fn summary_reports_kernel_rtt_when_sampled() {
+        let mut summary = sample_summary(None, None, OUTPUT_SCHEMA_V1);
+        assert_eq!(kernel_rtt_line(&summary), None);
+        let json = to_string(&JsonSummary::from(&summary)).expect("serialize JsonSummary");
+        assert!(!json.contains("tcp_info"));
+
+        let mut kernel = TcpInfoStats::new(3);
+        kernel.min_rtt_ms = 0.05;
+        kernel.avg_rtt_ms = 0.064;
+        kernel.max_rtt_ms = 0.08;
+        kernel.avg_rttvar_ms = 0.025;
+        kernel.total_retransmits = 1;
+        summary.tcp_info = Some(kernel);
+        assert_eq!(
+            kernel_rtt_line(&summary).unwrap(),
+            "Kernel RTT min/avg/max = 0.050/0.064/0.080 ms, rttvar avg = 0.025 ms, retransmits = 1"
+        );
+        let json = to_string(&JsonSummary::from(&summary)).expect("serialize JsonSummary");
+        assert!(json.contains("\"tcp_info\":{\"samples\":3,\"min_rtt_ms\":0.05"));
+    }
+
+    #[test]
+    fn retransmitted_probes_are_flagged() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(!json.contains("retransmitted"));
+        assert!(
+            !Normal::new(None)
+                .render_probe(&res)
+                .contains("retransmitted")
+        );
+
+        res.retransmitted = true;
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains("\"retransmitted\":true"));
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with("42.0000 ms [SYN retransmitted]")
+        );
+    }
+
+    #[test]
+    fn held_connections_report_how_they_ended() {
+        let mut res = sample_result(false, None, None, OUTPUT_SCHEMA_V1);
+        res.outcome = ProbeOutcome::OpenThenReset;
+        res.time_to_close_ms = Some(12.5);
+
+        assert_eq!(
+            Normal::new(None).render_probe(&res),
+            "Probing 127.0.0.1:80/tcp - open_then_reset - 42.0000 ms, closed by server after 12.5000 ms"
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains("\"outcome\":\"open_then_reset\""));
+        assert!(json.contains("\"time_to_close_ms\":12.5"));
+        assert_eq!(Csv::probe_fields(&res)[2], "open_then_reset");
+    }
+
+    #[test]
+    fn banner_and_ttfb_are_reported() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.time_to_first_byte_ms = Some(1.5);
+        res.banner = Some("SSH-2.0-OpenSSH_9.6".into());
+
+        assert!(
+            Normal::new(None).render_probe(&res).ends_with(
+                "42.0000 ms, first byte after 1.5000 ms, banner \"SSH-2.0-OpenSSH_9.6\""
+            )
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains("\"time_to_first_byte_ms\":1.5,\"banner\":\"SSH-2.0-OpenSSH_9.6\""));
+
+        res.success = false;
+        res.outcome = ProbeOutcome::BannerMismatch;
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .contains("- banner_mismatch -")
+        );
+    }
+
+    #[test]
+    fn step_timings_follow_the_connect_rtt() {
+        let mut res = sample_result(false, None, None, OUTPUT_SCHEMA_V1);
+        res.outcome = ProbeOutcome::StepFailed;
+        res.steps = vec![
+            StepResult {
+                action: "send",
+                success: true,
+                duration_ms: 0.05,
+                received: None,
+                error: None,
+            },
+            StepResult {
+                action: "expect",
+                success: false,
+                duration_ms: 0.5,
+                received: Some("-ERR".into()),
+                error: Some("unexpected response".into()),
+            },
+        ];
+
+        assert_eq!(
+            Normal::new(None).render_probe(&res),
+            "Probing 127.0.0.1:80/tcp - step_failed - 42.0000 ms, steps: send 0.0500 ms, \
+             expect failed after 0.5000 ms (unexpected response, got \"-ERR\")"
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(
+            json.contains(
+                "\"steps\":[{\"action\":\"send\",\"success\":true,\"duration_ms\":0.05},"
+            )
+        );
+    }
+
+    #[test]
+    fn tls_details_follow_the_connect_rtt() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.tls = Some(TlsInfo {
+            handshake_ms: 3.1,
+            starttls_ms: None,
+            version: Some("TLSv1.3".into()),
+            cipher: Some("TLS13_AES_128_GCM_SHA256".into()),
+            alpn: Some("h2".into()),
+            error: None,
+            certificate: None,
+        });
+        assert_eq!(
+            Normal::new(None).render_probe(&res),
+            "Probing 127.0.0.1:80/tcp - open - 42.0000 ms, TLSv1.3 TLS13_AES_128_GCM_SHA256 \
+             alpn=h2 handshake 3.1000 ms"
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains(
+            "\"tls\":{\"handshake_ms\":3.1,\"version\":\"TLSv1.3\",\
+             \"cipher\":\"TLS13_AES_128_GCM_SHA256\",\"alpn\":\"h2\"}"
+        ));
+
+        res.outcome = ProbeOutcome::TlsFailed;
+        res.tls = Some(TlsInfo {
+            handshake_ms: 1.5,
+            starttls_ms: None,
+            version: None,
+            cipher: None,
+            alpn: None,
+            error: Some("invalid peer certificate: UnknownIssuer".into()),
+            certificate: None,
+        });
+        assert!(
+            Normal::new(None).render_probe(&res).ends_with(
+                ", TLS failed after 1.5000 ms (invalid peer certificate: UnknownIssuer)"
+            )
+        );
+    }
+
+    #[test]
+    fn http_status_and_timings_are_reported() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.http = Some(HttpInfo {
+            status: Some(204),
+            ttfb_ms: Some(1.2),
+            total_ms: 1.5,
+            body_bytes: 0,
+            error: None,
+        });
+        assert_eq!(
+            Normal::new(None).render_probe(&res),
+            "Probing 127.0.0.1:80/tcp - open - 42.0000 ms, HTTP 204 first byte 1.2000 ms \
+             total 1.5000 ms"
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains(
+            "\"http\":{\"status\":204,\"ttfb_ms\":1.2,\"total_ms\":1.5,\"body_bytes\":0}"
+        ));
+
+        res.outcome = ProbeOutcome::HttpFailed;
+        res.http = Some(HttpInfo {
+            status: None,
+            ttfb_ms: None,
+            total_ms: 2000.0,
+            body_bytes: 0,
+            error: Some("timed out".into()),
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with(", HTTP failed after 2000.0000 ms (timed out)")
+        );
+    }
+
+    #[test]
+    fn grpc_serving_status_is_reported() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.grpc = Some(GrpcInfo {
+            serving_status: Some("SERVING".into()),
+            grpc_status: Some(0),
+            rpc_ms: 2.1,
+            error: None,
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with(", gRPC SERVING in 2.1000 ms")
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains(
+            "\"grpc\":{\"serving_status\":\"SERVING\",\"grpc_status\":0,\"rpc_ms\":2.1}"
+        ));
+
+        res.outcome = ProbeOutcome::GrpcFailed;
+        res.grpc = Some(GrpcInfo {
+            serving_status: None,
+            grpc_status: Some(12),
+            rpc_ms: 0.8,
+            error: Some("UNIMPLEMENTED (12)".into()),
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with(", gRPC failed after 0.8000 ms (UNIMPLEMENTED (12))")
+        );
+    }
+
+    #[test]
+    fn protocol_reply_time_is_reported() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.protocol = Some(ProtocolInfo {
+            protocol: Protocol::Memcached,
+            rtt_ms: 0.21,
+            server_version: Some("1.6.21".into()),
+            error: None,
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with(", memcached 1.6.21 reply 0.2100 ms")
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains(
+            "\"protocol\":{\"name\":\"memcached\",\"rtt_ms\":0.21,\"server_version\":\"1.6.21\"}"
+        ));
+
+        res.outcome = ProbeOutcome::ProtocolError;
+        res.protocol = Some(ProtocolInfo {
+            protocol: Protocol::Redis,
+            rtt_ms: 0.4,
+            server_version: None,
+            error: Some("PING failed: NOAUTH Authentication required.".into()),
+        });
+        assert!(Normal::new(None).render_probe(&res).ends_with(
+            ", redis failed after 0.4000 ms (PING failed: NOAUTH Authentication required.)"
+        ));
+    }
+
+    #[test]
+    fn dns_rcode_and_query_time_are_reported() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.dns = Some(DnsInfo {
+            rcode: Some("NOERROR".into()),
+            answers: 1,
+            query_ms: 0.9,
+            error: None,
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with(", DNS NOERROR 1 answer in 0.9000 ms")
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains("\"dns\":{\"rcode\":\"NOERROR\",\"answers\":1,\"query_ms\":0.9}"));
+
+        res.outcome = ProbeOutcome::DnsRcodeMismatch;
+        res.dns = Some(DnsInfo {
+            rcode: Some("SERVFAIL".into()),
+            answers: 0,
+            query_ms: 12.5,
+            error: None,
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with(", DNS SERVFAIL 0 answers in 12.5000 ms")
+        );
+    }
+
+    #[test]
+    fn proxy_tunnel_time_is_reported() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.proxy = Some(ProxyInfo {
+            kind: ProxyKind::Socks5h,
+            target: "db.internal:5432".into(),
+            tunnel_ms: 12.3,
+            error: None,
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with("42.0000 ms, socks5h tunnel to db.internal:5432 in 12.3000 ms")
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains(
+            "\"proxy\":{\"kind\":\"socks5h\",\"target\":\"db.internal:5432\",\"tunnel_ms\":12.3}"
+        ));
+
+        res.outcome = ProbeOutcome::ProxyDenied;
+        res.proxy = Some(ProxyInfo {
+            kind: ProxyKind::Http,
+            target: "db.internal:5432".into(),
+            tunnel_ms: 1.5,
+            error: Some("proxy answered 403 Forbidden".into()),
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with(", http proxy failed after 1.5000 ms (proxy answered 403 Forbidden)")
+        );
+    }
+
+    #[test]
+    fn starttls_time_precedes_the_handshake() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.tls = Some(TlsInfo {
+            handshake_ms: 3.1,
+            starttls_ms: Some(0.4),
+            version: Some("TLSv1.3".into()),
+            cipher: None,
+            alpn: None,
+            error: None,
+            certificate: None,
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with("42.0000 ms, STARTTLS 0.4000 ms, TLSv1.3 handshake 3.1000 ms")
+        );
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains("\"tls\":{\"handshake_ms\":3.1,\"starttls_ms\":0.4,"));
+
+        res.outcome = ProbeOutcome::StartTlsFailed;
+        res.tls = Some(TlsInfo {
+            handshake_ms: 0.0,
+            starttls_ms: Some(1.25),
+            version: None,
+            cipher: None,
+            alpn: None,
+            error: Some("server does not offer STARTTLS".into()),
+            certificate: None,
+        });
+        assert!(
+            Normal::new(None)
+                .render_probe(&res)
+                .ends_with(", STARTTLS failed after 1.2500 ms (server does not offer STARTTLS)")
+        );
+    }
+
+    #[test]
+    fn certificate_is_summarised_with_warnings() {
+        let mut summary = sample_summary(None, None, OUTPUT_SCHEMA_V1);
+        assert!(certificate_lines(&summary).is_empty());
+
+        summary.certificate = Some(CertInfo {
+            subject: "CN=example.com".into(),
+            issuer: "CN=Example CA".into(),
+            sans: vec!["example.com".into(), "www.example.com".into()],
+            not_after: "2030-01-15T00:00:00Z".into(),
+            days_remaining: 5,
+            chain_len: 2,
+            name_matches: false,
+        });
+        summary.cert_warn_days = Some(14);
+        assert_eq!(
+            certificate_lines(&summary),
+            [
+                "Certificate: CN=example.com (issuer CN=Example CA)",
+                "Certificate expires 2030-01-15T00:00:00Z (5 days), chain length 2, \
+                 SANs: example.com, www.example.com",
+                "WARNING: certificate does not cover the target name",
+                "WARNING: certificate expires within 14 days",
+            ]
+        );
+
+        let json = to_string(&JsonSummary::from(&summary)).expect("serialize JsonSummary");
+        assert!(json.contains("\"days_remaining\":5,\"chain_len\":2,\"name_matches\":false}"));
+        assert!(json.contains("\"cert_warn_days\":14,\"cert_expiring\":true"));
+    }
+
+    #[test]
+    fn close_mode_is_reported() {
+        let mut summary = sample_summary(None, None, OUTPUT_SCHEMA_V1);
+        assert_eq!(close_mode_line(&summary), None);
+        let json = to_string(&JsonSummary::from(&summary)).expect("serialize JsonSummary");
+        assert!(json.contains("\"close_mode\":\"fin\""));
+
+        summary.close_mode = CloseMode::Rst;
+        assert!(close_mode_line(&summary).unwrap().contains("RST"));
+
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        res.close_mode = Some(CloseMode::HalfClose);
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains("\"close_mode\":\"half_close\""));
+
+        let failed = sample_result(false, None, None, OUTPUT_SCHEMA_V1);
+        let json = to_string(&JsonProbe::from(&failed)).expect("serialize JsonProbe");
+        assert!(!json.contains("close_mode"));
+    }
+
+    #[test]
+    fn json_echoes_socket_options() {
+        let mut res = sample_result(true, None, None, OUTPUT_SCHEMA_V1);
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(!json.contains("socket_options"));
+
+        let mut options = SocketOptions::default();
+        options.ttl = Some(3);
+        options.tos = Some(184);
+        res.socket_options = Some(options);
+        let json = to_string(&JsonProbe::from(&res)).expect("serialize JsonProbe");
+        assert!(json.contains("\"socket_options\":{\"ttl\":3,\"tos\":184,\"dscp\":46}"));
+    }
+
     #[test]
     fn json_omits_timestamp_fields_when_disabled() {
         let summary = JsonSummary::from(&sample_summary(None, None, OUTPUT_SCHEMA_V1));
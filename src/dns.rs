@@ -0,0 +1,791 @@
+//! Minimal DNS wire encoding and query transport.
+//!
+//! `tokio::net::lookup_host` only returns socket addresses, so record types
+//! such as SRV are queried directly against a nameserver over UDP, retrying
+//! over TCP when the answer is truncated. DNS probes send one query over the
+//! probe connection itself.
+
+use crate::{
+    error::{Result, TcpingError},
+    probe::ProbeOutcome,
+    wire::Wire,
+};
+use socket2::Socket;
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+    time,
+};
+
+pub(crate) const TYPE_PTR: u16 = 12;
+pub(crate) const TYPE_SRV: u16 = 33;
+
+pub(crate) const DNS_PORT: u16 = 53;
+pub(crate) const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CLASS_IN: u16 = 1;
+const FLAG_RD: u16 = 0x0100;
+const FLAG_TC: u16 = 0x0200;
+const HEADER_LEN: usize = 12;
+const MAX_NAME_LEN: usize = 255;
+const MAX_LABEL_LEN: usize = 63;
+const MAX_POINTER_JUMPS: usize = 64;
+const MAX_UDP_RESPONSE: usize = 4_096;
+
+pub(crate) const RCODE_NOERROR: u8 = 0;
+pub(crate) const RCODE_NXDOMAIN: u8 = 3;
+
+/// Decoded DNS response. Authority and additional sections are not kept.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Message {
+    pub(crate) id: u16,
+    pub(crate) rcode: u8,
+    pub(crate) truncated: bool,
+    pub(crate) answers: Vec<Record>,
+}
+
+/// One resource record from the answer section.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Record {
+    pub(crate) name: String,
+    pub(crate) rtype: u16,
+    pub(crate) ttl: u32,
+    pub(crate) data: RecordData,
+}
+
+/// RDATA for the record types tcping understands.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RecordData {
+    Ptr(String),
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    Other(Vec<u8>),
+}
+
+impl Message {
+    /// Turn a non-zero response code into an error naming the queried name.
+    pub(crate) fn check_rcode(&self, name: &str) -> Result<()> {
+        match self.rcode {
+            RCODE_NOERROR => Ok(()),
+            RCODE_NXDOMAIN => Err(TcpingError::Dns(format!("{name} does not exist"))),
+            rcode => Err(TcpingError::Dns(format!(
+                "query for {name} failed: {}",
+                rcode_label(rcode)
+            ))),
+        }
+    }
+}
+
+/// Mnemonics of response codes 0 to 10, indexed by code.
+const RCODE_NAMES: [&str; 11] = [
+    "NOERROR", "FORMERR", "SERVFAIL", "NXDOMAIN", "NOTIMP", "REFUSED", "YXDOMAIN", "YXRRSET",
+    "NXRRSET", "NOTAUTH", "NOTZONE",
+];
+
+/// Mnemonic for a DNS response code, if it has one.
+pub(crate) fn rcode_name(rcode: u8) -> Option<&'static str> {
+    RCODE_NAMES.get(usize::from(rcode)).copied()
+}
+
+/// `NOERROR`, or `RCODE12` for codes without a mnemonic.
+fn rcode_label(rcode: u8) -> String {
+    rcode_name(rcode).map_or_else(|| format!("RCODE{rcode}"), str::to_string)
+}
+
+/// Best-effort reader for the first DNS server listed in `/etc/resolv.conf`.
+/// Returns `None` on Windows or failure.
+pub(crate) fn system_nameserver() -> Option<IpAddr> {
+    system_nameservers().into_iter().next()
+}
+
+/// Every DNS server listed in `/etc/resolv.conf`, in order; empty on Windows
+/// or failure.
+pub(crate) fn system_nameservers() -> Vec<IpAddr> {
+    let mut servers = Vec::new();
+    #[cfg(unix)]
+    {
+        use std::fs::File;
+        use std::io::{BufRead, BufReader};
+
+        let Ok(file) = File::open("/etc/resolv.conf") else {
+            return servers;
+        };
+        for line in BufReader::new(file).lines().map_while(|line| line.ok()) {
+            let line = line.trim_start();
+            if let Some(rest) = line.strip_prefix("nameserver")
+                && let Ok(server) = rest.trim().parse()
+            {
+                servers.push(server);
+            }
+        }
+    }
+    servers
+}
+
+/// `in-addr.arpa` / `ip6.arpa` name used for PTR queries.
+pub(crate) fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::with_capacity(72);
+            for byte in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Look up the PTR name of `ip` through the system nameservers in turn,
+/// falling back to the OS resolver when none of them answers.
+///
+/// The annotation is best-effort, so every failure maps to `None`.
+pub(crate) async fn reverse_lookup(ip: IpAddr) -> Option<String> {
+    let name = reverse_name(ip);
+    for server in system_nameservers() {
+        let server = SocketAddr::new(server, DNS_PORT);
+        let Ok(response) = query(server, &name, TYPE_PTR, QUERY_TIMEOUT).await else {
+            continue;
+        };
+        return response
+            .answers
+            .into_iter()
+            .find_map(|record| match record.data {
+                RecordData::Ptr(name) => Some(name),
+                _ => None,
+            });
+    }
+    tokio::task::spawn_blocking(move || os_reverse_lookup(ip))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// PTR name from the OS resolver (`getnameinfo` with `NI_NAMEREQD`).
+#[cfg(unix)]
+fn os_reverse_lookup(ip: IpAddr) -> Option<String> {
+    let addr = socket2::SockAddr::from(SocketAddr::new(ip, 0));
+    let mut host = [0 as libc::c_char; 1025];
+    // SAFETY: `addr` is a valid socket address of `addr.len()` bytes and
+    // `host` is a writable buffer of the length passed.
+    let rc = unsafe {
+        libc::getnameinfo(
+            addr.as_ptr().cast(),
+            addr.len(),
+            host.as_mut_ptr(),
+            host.len() as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    name_from_buffer(rc, &host)
+}
+
+/// PTR name from the OS resolver (`getnameinfo` with `NI_NAMEREQD`).
+#[cfg(windows)]
+fn os_reverse_lookup(ip: IpAddr) -> Option<String> {
+    use std::ffi::{c_char, c_void};
+
+    const NI_NAMEREQD: i32 = 0x04;
+
+    #[link(name = "ws2_32")]
+    unsafe extern "system" {
+        fn getnameinfo(
+            addr: *const c_void,
+            addr_len: i32,
+            host: *mut c_char,
+            host_len: u32,
+            service: *mut c_char,
+            service_len: u32,
+            flags: i32,
+        ) -> i32;
+    }
+
+    let addr = socket2::SockAddr::from(SocketAddr::new(ip, 0));
+    let mut host = [0 as c_char; 1025];
+    // SAFETY: `addr` is a valid socket address of `addr.len()` bytes and
+    // `host` is a writable buffer of the length passed. Winsock is already
+    // initialised by the probe sockets.
+    let rc = unsafe {
+        getnameinfo(
+            addr.as_ptr().cast(),
+            addr.len(),
+            host.as_mut_ptr(),
+            host.len() as u32,
+            std::ptr::null_mut(),
+            0,
+            NI_NAMEREQD,
+        )
+    };
+    name_from_buffer(rc, &host)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn os_reverse_lookup(_ip: IpAddr) -> Option<String> {
+    None
+}
+
+#[cfg(any(unix, windows))]
+fn name_from_buffer(rc: i32, buf: &[std::ffi::c_char]) -> Option<String> {
+    if rc != 0 {
+        return None;
+    }
+    let bytes: Vec<u8> = buf
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8(bytes)
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+/// Encode a recursive query for `name` with one question of type `qtype`.
+pub(crate) fn encode_query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&FLAG_RD.to_be_bytes());
+    buf.extend_from_slice(&1_u16.to_be_bytes());
+    buf.extend_from_slice(&[0; 6]);
+    encode_name(&mut buf, name)?;
+    buf.extend_from_slice(&qtype.to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(buf)
+}
+
+fn encode_name(buf: &mut Vec<u8>, name: &str) -> Result<()> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let invalid = |reason: &str| TcpingError::Dns(format!("invalid DNS name '{name}': {reason}"));
+
+    if name.len() + 2 > MAX_NAME_LEN {
+        return Err(invalid("name is too long"));
+    }
+
+    if !name.is_empty() {
+        for label in name.split('.') {
+            if label.is_empty() {
+                return Err(invalid("empty label"));
+            }
+            if label.len() > MAX_LABEL_LEN {
+                return Err(invalid("label is longer than 63 bytes"));
+            }
+            if !label.is_ascii() {
+                return Err(invalid("non-ASCII label"));
+            }
+            buf.push(label.len() as u8);
+            buf.extend_from_slice(label.as_bytes());
+        }
+    }
+    buf.push(0);
+    Ok(())
+}
+
+/// Decode a DNS response, keeping only the answer section.
+pub(crate) fn decode(buf: &[u8]) -> Result<Message> {
+    let mut reader = Reader { buf, pos: 0 };
+    let id = reader.u16()?;
+    let flags = reader.u16()?;
+    let qdcount = reader.u16()?;
+    let ancount = reader.u16()?;
+    reader.skip(4)?;
+
+    for _ in 0..qdcount {
+        reader.name()?;
+        reader.skip(4)?;
+    }
+
+    let mut answers = Vec::with_capacity(usize::from(ancount));
+    for _ in 0..ancount {
+        let name = reader.name()?;
+        let rtype = reader.u16()?;
+        let _class = reader.u16()?;
+        let ttl = reader.u32()?;
+        let rdlength = usize::from(reader.u16()?);
+        let end = reader.pos + rdlength;
+        if end > buf.len() {
+            return Err(malformed("record data runs past end of message"));
+        }
+
+        let data = match rtype {
+            TYPE_PTR => RecordData::Ptr(reader.name()?),
+            TYPE_SRV => RecordData::Srv {
+                priority: reader.u16()?,
+                weight: reader.u16()?,
+                port: reader.u16()?,
+                target: reader.name()?,
+            },
+            _ => RecordData::Other(buf[reader.pos..end].to_vec()),
+        };
+        reader.pos = end;
+
+        answers.push(Record {
+            name,
+            rtype,
+            ttl,
+            data,
+        });
+    }
+
+    Ok(Message {
+        id,
+        rcode: (flags & 0x000f) as u8,
+        truncated: flags & FLAG_TC != 0,
+        answers,
+    })
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| malformed("message is truncated"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read a possibly compressed domain name. The root name decodes as `.`.
+    fn name(&mut self) -> Result<String> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut cursor = self.pos;
+        let mut resume = None;
+        let mut jumps = 0;
+
+        loop {
+            let len = *self
+                .buf
+                .get(cursor)
+                .ok_or_else(|| malformed("name is truncated"))?;
+            match len & 0xc0 {
+                0x00 if len == 0 => {
+                    cursor += 1;
+                    break;
+                }
+                0x00 => {
+                    let start = cursor + 1;
+                    let end = start + usize::from(len);
+                    let label = self
+                        .buf
+                        .get(start..end)
+                        .ok_or_else(|| malformed("label is truncated"))?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    cursor = end;
+                }
+                0xc0 => {
+                    let low = *self
+                        .buf
+                        .get(cursor + 1)
+                        .ok_or_else(|| malformed("compression pointer is truncated"))?;
+                    jumps += 1;
+                    if jumps > MAX_POINTER_JUMPS {
+                        return Err(malformed("compression pointer loop"));
+                    }
+                    resume.get_or_insert(cursor + 2);
+                    cursor = usize::from(u16::from_be_bytes([len & 0x3f, low]));
+                }
+                _ => return Err(malformed("unsupported label type")),
+            }
+        }
+
+        self.pos = resume.unwrap_or(cursor);
+        if labels.is_empty() {
+            Ok(".".into())
+        } else {
+            Ok(labels.join("."))
+        }
+    }
+}
+
+fn malformed(reason: &str) -> TcpingError {
+    TcpingError::Dns(format!("malformed DNS response: {reason}"))
+}
+
+/// Send one query to `server` and return the decoded response.
+///
+/// The query goes over UDP first and is repeated over TCP when the server
+/// sets the truncation bit.
+pub(crate) async fn query(
+    server: SocketAddr,
+    name: &str,
+    qtype: u16,
+    timeout: Duration,
+) -> Result<Message> {
+    let id = query_id();
+    let request = encode_query(id, name, qtype)?;
+
+    let response = time::timeout(timeout, query_udp(server, &request))
+        .await
+        .map_err(|_| timed_out(server))??;
+    let mut message = decode(&response)?;
+
+    if message.truncated {
+        let response = time::timeout(timeout, query_tcp(server, &request))
+            .await
+            .map_err(|_| timed_out(server))??;
+        message = decode(&response)?;
+    }
+
+    if message.id != id {
+        return Err(malformed("response ID does not match query"));
+    }
+
+    Ok(message)
+}
+
+async fn query_udp(server: SocketAddr, request: &[u8]) -> Result<Vec<u8>> {
+    let local = match server {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(server).await?;
+    socket.send(request).await?;
+
+    let mut buf = vec![0; MAX_UDP_RESPONSE];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+        // Skip stray or spoofed datagrams; the caller's timeout bounds the wait.
+        if len >= 2 && buf[..2] == request[..2] {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+    }
+}
+
+async fn query_tcp(server: SocketAddr, request: &[u8]) -> Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(server).await?;
+    stream.write_all(&frame_tcp(request)).await?;
+
+    let len = stream.read_u16().await?;
+    let mut buf = vec![0; usize::from(len)];
+    stream.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Prefix a message with the two-byte length used by DNS over TCP.
+pub(crate) fn frame_tcp(message: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(message.len() + 2);
+    framed.extend_from_slice(&(message.len() as u16).to_be_bytes());
+    framed.extend_from_slice(message);
+    framed
+}
+
+/// Unpredictable query ID: a counter and the clock hashed with the standard
+/// library's randomly keyed SipHash.
+fn query_id() -> u16 {
+    use std::{
+        hash::{BuildHasher, Hasher, RandomState},
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    static QUERIES: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(QUERIES.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos()),
+    );
+    hasher.finish() as u16
+}
+
+fn timed_out(server: SocketAddr) -> TcpingError {
+    TcpingError::Dns(format!("no response from DNS server {server}"))
+}
+
+/// Record types DNS probes can ask for by name.
+const QUERY_TYPES: [(&str, u16); 14] = [
+    ("A", 1),
+    ("NS", 2),
+    ("CNAME", 5),
+    ("SOA", 6),
+    ("PTR", TYPE_PTR),
+    ("MX", 15),
+    ("TXT", 16),
+    ("AAAA", 28),
+    ("SRV", TYPE_SRV),
+    ("DS", 43),
+    ("DNSKEY", 48),
+    ("HTTPS", 65),
+    ("ANY", 255),
+    ("CAA", 257),
+];
+
+/// Result of a DNS probe.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DnsInfo {
+    /// Response code, e.g. `NOERROR`.
+    pub rcode: Option<String>,
+    /// Records in the answer section.
+    pub answers: usize,
+    /// Time from sending the query to the complete response, apart from the
+    /// connect RTT.
+    pub query_ms: f64,
+    /// Why no usable response arrived.
+    pub error: Option<String>,
+}
+
+/// Query sent by DNS probes over the probe connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DnsQuery {
+    pub(crate) name: String,
+    pub(crate) qtype: u16,
+    /// Response codes that count as success.
+    pub(crate) expect: Vec<u8>,
+}
+
+impl DnsQuery {
+    /// `qtype` is a mnemonic such as `AAAA` or `TYPE65`.
+    pub(crate) fn new(name: &str, qtype: &str) -> Result<Self> {
+        let qtype = parse_type(qtype)?;
+        encode_query(0, name, qtype)?;
+        Ok(Self {
+            name: name.to_string(),
+            qtype,
+            expect: vec![RCODE_NOERROR],
+        })
+    }
+
+    /// Comma-separated response codes, by name (`NXDOMAIN`, `RCODE12`) or
+    /// number.
+    pub(crate) fn parse_rcodes(spec: &str) -> Result<Vec<u8>> {
+        spec.split(',')
+            .map(|code| {
+                let code = code.trim();
+                let upper = code.to_ascii_uppercase();
+                RCODE_NAMES
+                    .iter()
+                    .position(|&name| name == upper)
+                    .and_then(|rcode| u8::try_from(rcode).ok())
+                    .or_else(|| upper.strip_prefix("RCODE").unwrap_or(&upper).parse().ok())
+                    .filter(|&rcode| rcode < 16)
+                    .ok_or_else(|| {
+                        TcpingError::InvalidOptions(format!("unknown DNS response code '{code}'"))
+                    })
+            })
+            .collect()
+    }
+}
+
+fn parse_type(qtype: &str) -> Result<u16> {
+    let upper = qtype.trim().to_ascii_uppercase();
+    QUERY_TYPES
+        .iter()
+        .find(|(name, _)| *name == upper)
+        .map(|&(_, value)| value)
+        .or_else(|| upper.strip_prefix("TYPE")?.parse().ok())
+        .ok_or_else(|| TcpingError::InvalidOptions(format!("unknown DNS record type '{qtype}'")))
+}
+
+/// Send `query` over `io` (plain TCP or TLS) and check the response code.
+pub(crate) fn exchange<S: Read + Write>(
+    io: &mut S,
+    socket: &Socket,
+    query: &DnsQuery,
+    timeout: Duration,
+) -> (ProbeOutcome, DnsInfo) {
+    let start = Instant::now();
+    let result = roundtrip(io, socket, query, timeout);
+    let query_ms = start.elapsed().as_secs_f64() * 1_000.0;
+
+    match result {
+        Ok(message) => {
+            let outcome = if query.expect.contains(&message.rcode) {
+                ProbeOutcome::Open
+            } else {
+                ProbeOutcome::DnsRcodeMismatch
+            };
+            let info = DnsInfo {
+                rcode: Some(rcode_label(message.rcode)),
+                answers: message.answers.len(),
+                query_ms,
+                error: None,
+            };
+            (outcome, info)
+        }
+        Err(error) => (
+            ProbeOutcome::DnsFailed,
+            DnsInfo {
+                rcode: None,
+                answers: 0,
+                query_ms,
+                error: Some(error),
+            },
+        ),
+    }
+}
+
+fn roundtrip<S: Read + Write>(
+    io: &mut S,
+    socket: &Socket,
+    query: &DnsQuery,
+    timeout: Duration,
+) -> std::result::Result<Message, String> {
+    let id = query_id();
+    let request = encode_query(id, &query.name, query.qtype).map_err(|err| err.to_string())?;
+    let mut wire = Wire::new(io, socket, timeout, usize::from(u16::MAX));
+    wire.send(&frame_tcp(&request))?;
+    let len = wire.exact(2)?;
+    let response = wire.exact(usize::from(u16::from_be_bytes([len[0], len[1]])))?;
+
+    let message = decode(&response).map_err(|err| match err {
+        TcpingError::Dns(reason) => reason,
+        err => err.to_string(),
+    })?;
+    if message.id != id {
+        return Err("response ID does not match query".into());
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_query_with_single_question() {
+        let query = encode_query(0x1234, "_ldap._tcp.example.com.", TYPE_SRV).unwrap();
+        assert_eq!(
+            &query[..HEADER_LEN],
+            &[0x12, 0x34, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            &query[HEADER_LEN..],
+            b"\x05_ldap\x04_tcp\x07example\x03com\x00\x00\x21\x00\x01"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert!(encode_query(1, "a..b", TYPE_SRV).is_err());
+        assert!(encode_query(1, &"a".repeat(64), TYPE_SRV).is_err());
+        assert!(encode_query(1, "例子.测试", TYPE_SRV).is_err());
+    }
+
+    #[test]
+    fn decodes_srv_answer_with_compressed_names() {
+        let mut response = encode_query(7, "_ldap._tcp.example.com", TYPE_SRV).unwrap();
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 1;
+        response.extend_from_slice(&[0xc0, 0x0c, 0, 33, 0, 1, 0, 0, 0x0e, 0x10, 0, 13]);
+        response.extend_from_slice(&[0, 10, 0, 5, 0x01, 0x85]);
+        response.extend_from_slice(b"\x04ldap\xc0\x17");
+
+        let message = decode(&response).unwrap();
+        assert_eq!(message.id, 7);
+        assert_eq!(message.rcode, RCODE_NOERROR);
+        assert!(!message.truncated);
+        assert_eq!(message.answers.len(), 1);
+        assert_eq!(message.answers[0].name, "_ldap._tcp.example.com");
+        assert_eq!(message.answers[0].ttl, 3600);
+        assert_eq!(
+            message.answers[0].data,
+            RecordData::Srv {
+                priority: 10,
+                weight: 5,
+                port: 389,
+                target: "ldap.example.com".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_query_types_and_rcodes() {
+        assert_eq!(DnsQuery::new("example.com", "aaaa").unwrap().qtype, 28);
+        assert_eq!(DnsQuery::new("example.com", "TYPE64").unwrap().qtype, 64);
+        assert!(DnsQuery::new("example.com", "BOGUS").is_err());
+        assert!(DnsQuery::new("a..b", "A").is_err());
+        assert_eq!(
+            DnsQuery::parse_rcodes("noerror, NXDOMAIN,5").unwrap(),
+            [0, 3, 5]
+        );
+        assert!(DnsQuery::parse_rcodes("16").is_err());
+        assert_eq!(DnsQuery::parse_rcodes("rcode12").unwrap(), [12]);
+        assert!(DnsQuery::parse_rcodes("unknown").is_err());
+        assert_eq!(rcode_name(3), Some("NXDOMAIN"));
+        assert_eq!(rcode_name(11), None);
+        assert_eq!(rcode_label(12), "RCODE12");
+    }
+
+    #[test]
+    fn builds_reverse_names() {
+        assert_eq!(
+            reverse_name("10.1.2.3".parse().unwrap()),
+            "3.2.1.10.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_name("2001:db8::567:89ab".parse().unwrap()),
+            "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    #[test]
+    fn decodes_ptr_answer() {
+        let mut response = encode_query(9, "3.2.1.10.in-addr.arpa", TYPE_PTR).unwrap();
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[7] = 1;
+        response.extend_from_slice(&[0xc0, 0x0c, 0, 12, 0, 1, 0, 0, 0, 60, 0, 9]);
+        response.extend_from_slice(b"\x03web\x03lan\x00");
+
+        let message = decode(&response).unwrap();
+        assert_eq!(message.answers[0].data, RecordData::Ptr("web.lan".into()));
+    }
+
+    #[test]
+    fn rejects_compression_loops_and_truncation() {
+        let mut response = encode_query(7, "a", TYPE_SRV).unwrap();
+        response[7] = 1;
+        response.extend_from_slice(&[0xc0, 0x13, 0xc0, 0x13]);
+        assert!(decode(&response).is_err());
+
+        assert!(decode(&[0, 1, 0x81]).is_err());
+    }
+
+    #[test]
+    fn reports_rcode_by_name() {
+        let message = Message {
+            id: 1,
+            rcode: 2,
+            truncated: false,
+            answers: Vec::new(),
+        };
+        let err = message.check_rcode("example.com").unwrap_err();
+        assert!(err.to_string().contains("SERVFAIL"));
+    }
+}
@@ -0,0 +1,55 @@
+//! Human-friendly duration parsing shared by CLI flags and step scripts.
+
+use crate::error::{Result, TcpingError};
+use std::time::Duration;
+
+/// Longest duration accepted: a year, far beyond any useful timeout yet small
+/// enough that adding it to an `Instant` cannot overflow.
+const MAX_DURATION: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Parse `250ms`, `2s`, `1.5s` or `1m`; a bare number is milliseconds.
+/// Durations below one millisecond or above a year are rejected.
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let invalid = |reason: String| TcpingError::InvalidOptions(reason);
+    let (number, unit_ms) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 1.0)
+    } else if let Some(secs) = value.strip_suffix('s') {
+        (secs, 1_000.0)
+    } else if let Some(mins) = value.strip_suffix('m') {
+        (mins, 60_000.0)
+    } else {
+        (value, 1.0)
+    };
+
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| invalid(format!("'{value}' is not a valid duration")))?;
+    let ms = number * unit_ms;
+    if !ms.is_finite() || ms < 1.0 {
+        return Err(invalid("duration must be at least 1ms".into()));
+    }
+    Duration::try_from_secs_f64(ms / 1_000.0)
+        .ok()
+        .filter(|duration| *duration <= MAX_DURATION)
+        .ok_or_else(|| invalid(format!("'{value}' is too long for a duration")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units_and_bare_milliseconds() {
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(
+            parse_duration("1.5s").unwrap(),
+            Duration::from_millis(1_500)
+        );
+        assert_eq!(parse_duration("1m").unwrap(), Duration::from_secs(60));
+        assert_eq!(parse_duration("40").unwrap(), Duration::from_millis(40));
+        for value in ["0s", "0.5ms", "soon", "", "-1s", "1e300s", "600000m"] {
+            assert!(parse_duration(value).is_err(), "{value}");
+        }
+    }
+}
@@ -14,6 +14,9 @@ pub enum TcpingError {
     #[error("target did not resolve to any socket address")]
     NoAddress,
 
+    #[error("DNS error: {0}")]
+    Dns(String),
+
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -0,0 +1,580 @@
+//! gRPC health check (`grpc.health.v1.Health/Check`) over a minimal HTTP/2
+//! client, on a plaintext connection (h2c with prior knowledge) or after the
+//! TLS handshake.
+//!
+//! One call is made on stream 1. Only what the call needs is implemented:
+//! SETTINGS and PING are acknowledged, flow control is left at the defaults
+//! (a health response is far below the initial window) and push is disabled.
+
+use crate::{
+    banner::sanitise,
+    hpack::{self, Decoder},
+    probe::ProbeOutcome,
+    protocol::percent_decode,
+    wire::Wire,
+};
+use socket2::Socket;
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const HEALTH_CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+
+/// Largest frame payload accepted (the protocol default is 16 KiB).
+const MAX_FRAME: usize = 1 << 20;
+
+/// Largest header block or response body accepted across all its frames; a
+/// health check reply is a few bytes.
+const MAX_RESPONSE: usize = 1 << 20;
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const CONTINUATION: u8 = 0x9;
+
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+const STREAM: u32 = 1;
+
+/// Result of a gRPC health check.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GrpcInfo {
+    /// `SERVING`, `NOT_SERVING`, `UNKNOWN` or `SERVICE_UNKNOWN`.
+    pub serving_status: Option<String>,
+    /// gRPC status code of the call; 0 is OK.
+    pub grpc_status: Option<u32>,
+    /// Time from sending the call to the end of the response.
+    pub rpc_ms: f64,
+    /// Why the call failed, e.g. an unknown service or a reset stream.
+    pub error: Option<String>,
+}
+
+/// Health check to run after connect (and TLS).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct GrpcConfig {
+    /// Service to ask about; empty for the server as a whole.
+    pub(crate) service: String,
+    /// `:authority`; the target (and non-default port) when unset.
+    pub(crate) authority: Option<String>,
+}
+
+/// Call `Health/Check` on `io` and classify the serving status.
+pub(crate) fn check<S: Read + Write>(
+    io: &mut S,
+    socket: &Socket,
+    config: &GrpcConfig,
+    tls: bool,
+    timeout: Duration,
+) -> (ProbeOutcome, GrpcInfo) {
+    let mut conn = Http2 {
+        wire: Wire::new(io, socket, timeout, MAX_FRAME)
+            .closed_with("connection closed before the response was complete"),
+    };
+    let result = conn
+        .call(config, tls)
+        .and_then(|response| response.serving_status());
+    let rpc_ms = conn.wire.elapsed_ms();
+
+    let (outcome, info) = match result {
+        Ok(status) => (
+            if status == 1 {
+                ProbeOutcome::Open
+            } else {
+                ProbeOutcome::GrpcNotServing
+            },
+            GrpcInfo {
+                serving_status: Some(serving_status_name(status)),
+                grpc_status: Some(0),
+                rpc_ms,
+                error: None,
+            },
+        ),
+        Err((grpc_status, error)) => (
+            ProbeOutcome::GrpcFailed,
+            GrpcInfo {
+                serving_status: None,
+                grpc_status,
+                rpc_ms,
+                error: Some(error),
+            },
+        ),
+    };
+    (outcome, info)
+}
+
+/// `grpc.health.v1.HealthCheckResponse.ServingStatus` names.
+fn serving_status_name(status: u64) -> String {
+    match status {
+        0 => "UNKNOWN".into(),
+        1 => "SERVING".into(),
+        2 => "NOT_SERVING".into(),
+        3 => "SERVICE_UNKNOWN".into(),
+        other => other.to_string(),
+    }
+}
+
+/// Canonical names of gRPC status codes.
+fn grpc_status_name(code: u32) -> &'static str {
+    match code {
+        0 => "OK",
+        1 => "CANCELLED",
+        2 => "UNKNOWN",
+        3 => "INVALID_ARGUMENT",
+        4 => "DEADLINE_EXCEEDED",
+        5 => "NOT_FOUND",
+        6 => "ALREADY_EXISTS",
+        7 => "PERMISSION_DENIED",
+        8 => "RESOURCE_EXHAUSTED",
+        9 => "FAILED_PRECONDITION",
+        10 => "ABORTED",
+        11 => "OUT_OF_RANGE",
+        12 => "UNIMPLEMENTED",
+        13 => "INTERNAL",
+        14 => "UNAVAILABLE",
+        15 => "DATA_LOSS",
+        16 => "UNAUTHENTICATED",
+        _ => "UNRECOGNIZED",
+    }
+}
+
+/// Failure with the gRPC status code, if the call got that far.
+type CallError = (Option<u32>, String);
+
+/// Headers (and trailers) and the DATA of the response stream.
+#[derive(Debug, Default)]
+struct Response {
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        // Trailers come last, so they win over response headers.
+        self.headers
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Serving status from a successful call.
+    fn serving_status(&self) -> Result<u64, CallError> {
+        match self.header(":status") {
+            Some("200") => {}
+            Some(status) => return Err((None, format!("HTTP status {status}"))),
+            None => return Err((None, "response without :status".into())),
+        }
+        let code: u32 = self
+            .header("grpc-status")
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| (None, "response without grpc-status".to_string()))?;
+        if code != 0 {
+            let message = self
+                .header("grpc-message")
+                // Percent-encoded per the gRPC over HTTP/2 spec.
+                .map(|message| format!(": {}", sanitise(percent_decode(message).as_bytes())))
+                .unwrap_or_default();
+            return Err((
+                Some(code),
+                format!("{} ({code}){message}", grpc_status_name(code)),
+            ));
+        }
+
+        let invalid = || (Some(0), "malformed HealthCheckResponse".to_string());
+        // Length-prefixed message: compressed flag, 4-byte length, protobuf.
+        let message = match self.body.as_slice() {
+            [0, len @ ..] if len.len() >= 4 => {
+                let size = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+                len[4..].get(..size).ok_or_else(invalid)?
+            }
+            _ => return Err(invalid()),
+        };
+        serving_status_field(message).ok_or_else(invalid)
+    }
+}
+
+/// Field 1 (`status`, varint) of a HealthCheckResponse; absent means 0.
+fn serving_status_field(mut message: &[u8]) -> Option<u64> {
+    let mut status = 0;
+    while !message.is_empty() {
+        let key = varint(&mut message)?;
+        match key & 0x7 {
+            0 => {
+                let value = varint(&mut message)?;
+                if key >> 3 == 1 {
+                    status = value;
+                }
+            }
+            1 => message = message.get(8..)?,
+            2 => {
+                let len = usize::try_from(varint(&mut message)?).ok()?;
+                message = message.get(len..)?;
+            }
+            5 => message = message.get(4..)?,
+            _ => return None,
+        }
+    }
+    Some(status)
+}
+
+fn varint(input: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first()?;
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn encode_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Frame {
+    kind: u8,
+    flags: u8,
+    stream: u32,
+    payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Payload without padding (and the priority block of HEADERS).
+    fn data(&self) -> Result<&[u8], String> {
+        let mut payload = self.payload.as_slice();
+        let mut pad = 0;
+        if self.flags & PADDED != 0 {
+            let (&len, rest) = payload.split_first().ok_or("malformed padded frame")?;
+            pad = usize::from(len);
+            payload = rest;
+        }
+        if self.kind == HEADERS && self.flags & PRIORITY != 0 {
+            payload = payload.get(5..).ok_or("malformed HEADERS priority")?;
+        }
+        payload
+            .get(
+                ..payload
+                    .len()
+                    .checked_sub(pad)
+                    .ok_or("malformed padded frame")?,
+            )
+            .ok_or_else(|| "malformed padded frame".into())
+    }
+
+    fn error_code(&self, offset: usize) -> u32 {
+        self.payload.get(offset..offset + 4).map_or(0, |code| {
+            u32::from_be_bytes([code[0], code[1], code[2], code[3]])
+        })
+    }
+}
+
+/// Blocking HTTP/2 connection with an overall deadline.
+struct Http2<'a, S> {
+    wire: Wire<'a, S>,
+}
+
+impl<S: Read + Write> Http2<'_, S> {
+    fn call(&mut self, config: &GrpcConfig, tls: bool) -> Result<Response, CallError> {
+        self.request(config, tls).map_err(|error| (None, error))?;
+        self.response().map_err(|error| (None, error))
+    }
+
+    /// Preface, SETTINGS and the whole call on stream 1.
+    fn request(&mut self, config: &GrpcConfig, tls: bool) -> Result<(), String> {
+        let mut out = PREFACE.to_vec();
+        // SETTINGS_ENABLE_PUSH = 0.
+        write_frame(&mut out, SETTINGS, 0, 0, &[0, 2, 0, 0, 0, 0]);
+
+        let mut block = Vec::new();
+        hpack::encode_indexed(&mut block, 3); // :method POST
+        hpack::encode_indexed(&mut block, if tls { 7 } else { 6 }); // :scheme
+        hpack::encode_indexed_name(&mut block, 4, HEALTH_CHECK_PATH);
+        hpack::encode_indexed_name(
+            &mut block,
+            1,
+            config.authority.as_deref().unwrap_or_default(),
+        );
+        hpack::encode_literal(&mut block, "content-type", "application/grpc");
+        hpack::encode_literal(&mut block, "te", "trailers");
+        hpack::encode_literal(
+            &mut block,
+            "user-agent",
+            concat!("tcping/", env!("CARGO_PKG_VERSION")),
+        );
+        write_frame(&mut out, HEADERS, END_HEADERS, STREAM, &block);
+
+        // HealthCheckRequest { service = 1 }, omitted when empty.
+        let mut message = Vec::new();
+        if !config.service.is_empty() {
+            message.push(0x0a);
+            encode_varint(&mut message, config.service.len() as u64);
+            message.extend_from_slice(config.service.as_bytes());
+        }
+        let mut body = vec![0];
+        body.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        body.extend_from_slice(&message);
+        write_frame(&mut out, DATA, END_STREAM, STREAM, &body);
+
+        self.wire.send(&out)
+    }
+
+    /// Read frames until stream 1 ends.
+    fn response(&mut self) -> Result<Response, String> {
+        let mut decoder = Decoder::default();
+        let mut response = Response::default();
+        loop {
+            let frame = self.frame()?;
+            match frame.kind {
+                SETTINGS if frame.flags & ACK == 0 => {
+                    let mut ack = Vec::new();
+                    write_frame(&mut ack, SETTINGS, ACK, 0, &[]);
+                    self.wire.send(&ack)?;
+                }
+                PING if frame.flags & ACK == 0 => {
+                    let mut ack = Vec::new();
+                    write_frame(&mut ack, PING, ACK, 0, &frame.payload);
+                    self.wire.send(&ack)?;
+                }
+                GOAWAY => {
+                    let last_stream = frame.error_code(0) & 0x7fff_ffff;
+                    let code = frame.error_code(4);
+                    if code != 0 || last_stream < STREAM {
+                        return Err(format!("server sent GOAWAY (error code {code})"));
+                    }
+                }
+                RST_STREAM if frame.stream == STREAM => {
+                    return Err(format!(
+                        "server reset the stream (error code {})",
+                        frame.error_code(0)
+                    ));
+                }
+                HEADERS if frame.stream == STREAM => {
+                    let mut block = frame.data()?.to_vec();
+                    let mut end_headers = frame.flags & END_HEADERS != 0;
+                    while !end_headers {
+                        let next = self.frame()?;
+                        if next.kind != CONTINUATION || next.stream != STREAM {
+                            return Err("header block interrupted".into());
+                        }
+                        if block.len() + next.payload.len() > MAX_RESPONSE {
+                            return Err("HTTP/2 header block too large".into());
+                        }
+                        block.extend_from_slice(&next.payload);
+                        end_headers = next.flags & END_HEADERS != 0;
+                    }
+                    response.headers.extend(decoder.decode(&block)?);
+                    if frame.flags & END_STREAM != 0 {
+                        return Ok(response);
+                    }
+                }
+                DATA if frame.stream == STREAM => {
+                    let data = frame.data()?;
+                    if response.body.len() + data.len() > MAX_RESPONSE {
+                        return Err("gRPC response body too large".into());
+                    }
+                    response.body.extend_from_slice(data);
+                    if frame.flags & END_STREAM != 0 {
+                        return Ok(response);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn frame(&mut self) -> Result<Frame, String> {
+        let head = self.wire.exact(9)?;
+        let len = usize::from(head[0]) << 16 | usize::from(head[1]) << 8 | usize::from(head[2]);
+        if len > MAX_FRAME {
+            return Err(format!("HTTP/2 frame too large ({len} bytes)"));
+        }
+        Ok(Frame {
+            kind: head[3],
+            flags: head[4],
+            stream: u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff,
+            payload: self.wire.exact(len)?,
+        })
+    }
+}
+
+fn write_frame(out: &mut Vec<u8>, kind: u8, flags: u8, stream: u32, payload: &[u8]) {
+    out.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(&[kind, flags]);
+    out.extend_from_slice(&stream.to_be_bytes());
+    out.extend_from_slice(payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(headers: &[(&str, &str)], body: &[u8]) -> Response {
+        Response {
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.to_vec(),
+        }
+    }
+
+    #[test]
+    fn serving_status_comes_from_the_message() {
+        let ok = [(":status", "200"), ("grpc-status", "0")];
+        assert_eq!(
+            response(&ok, b"\0\0\0\0\x02\x08\x01").serving_status(),
+            Ok(1)
+        );
+        assert_eq!(
+            response(&ok, b"\0\0\0\0\x02\x08\x02").serving_status(),
+            Ok(2)
+        );
+        // Proto3 omits the default value.
+        assert_eq!(response(&ok, b"\0\0\0\0\0").serving_status(), Ok(0));
+        assert!(response(&ok, b"\0\0\0\0\x05\x08").serving_status().is_err());
+    }
+
+    #[test]
+    fn grpc_errors_are_reported_with_their_message() {
+        let not_found = response(
+            &[
+                (":status", "200"),
+                ("grpc-status", "5"),
+                ("grpc-message", "unknown%20service"),
+            ],
+            b"",
+        );
+        assert_eq!(
+            not_found.serving_status(),
+            Err((Some(5), "NOT_FOUND (5): unknown service".into()))
+        );
+        assert_eq!(
+            response(&[(":status", "404")], b"").serving_status(),
+            Err((None, "HTTP status 404".into()))
+        );
+    }
+
+    #[test]
+    fn request_carries_the_service_name() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            // The request ends with the DATA frame carrying the message.
+            while !request.ends_with(b"\x0a\x03api") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            let mut reply = Vec::new();
+            write_frame(&mut reply, SETTINGS, 0, 0, &[]);
+            write_frame(&mut reply, PING, 0, 0, &[7; 8]);
+            // :status 200, content-type application/grpc (incremental indexing).
+            let mut block = vec![0x88, 0x5f];
+            block.push(16);
+            block.extend_from_slice(b"application/grpc");
+            write_frame(&mut reply, HEADERS, END_HEADERS, STREAM, &block);
+            write_frame(
+                &mut reply,
+                DATA,
+                PADDED,
+                STREAM,
+                b"\x02\0\0\0\0\x02\x08\x01pp",
+            );
+            let mut trailers = Vec::new();
+            hpack::encode_literal(&mut trailers, "grpc-status", "0");
+            write_frame(
+                &mut reply,
+                HEADERS,
+                END_HEADERS | END_STREAM,
+                STREAM,
+                &trailers,
+            );
+            stream.write_all(&reply).unwrap();
+
+            // SETTINGS and PING acknowledgements.
+            let mut acks = [0; 9 + 17];
+            stream.read_exact(&mut acks).unwrap();
+            request.extend_from_slice(&acks);
+            request
+        });
+
+        let socket = Socket::from(std::net::TcpStream::connect(addr).unwrap());
+        let config = GrpcConfig {
+            service: "api".into(),
+            authority: Some("grpc.example:50051".into()),
+        };
+        let (outcome, info) = check(
+            &mut &socket,
+            &socket,
+            &config,
+            false,
+            Duration::from_secs(1),
+        );
+        let request = server.join().unwrap();
+
+        assert!(request.starts_with(PREFACE));
+        let path = HEALTH_CHECK_PATH.as_bytes();
+        assert!(request.windows(path.len()).any(|window| window == path));
+        assert!(request.ends_with(&[0, 0, 8, PING, ACK, 0, 0, 0, 0, 7, 7, 7, 7, 7, 7, 7, 7]));
+        assert_eq!(outcome, ProbeOutcome::Open);
+        assert_eq!(info.serving_status.as_deref(), Some("SERVING"));
+        assert_eq!(info.grpc_status, Some(0));
+        assert_eq!(info.error, None);
+    }
+
+    #[test]
+    fn response_body_is_bounded() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reply = Vec::new();
+            write_frame(&mut reply, SETTINGS, 0, 0, &[]);
+            write_frame(&mut reply, HEADERS, END_HEADERS, STREAM, &[0x88]);
+            let mut data = Vec::new();
+            write_frame(&mut data, DATA, 0, STREAM, &[0; 1 << 14]);
+            // Keep streaming DATA without ever ending the stream.
+            if stream.write_all(&reply).is_ok() {
+                while stream.write_all(&data).is_ok() {}
+            }
+        });
+
+        let socket = Socket::from(std::net::TcpStream::connect(addr).unwrap());
+        let config = GrpcConfig {
+            service: String::new(),
+            authority: Some("grpc.example".into()),
+        };
+        let (outcome, info) = check(
+            &mut &socket,
+            &socket,
+            &config,
+            false,
+            Duration::from_secs(5),
+        );
+        drop(socket);
+        server.join().unwrap();
+
+        assert_eq!(outcome, ProbeOutcome::GrpcFailed);
+        assert_eq!(info.error.as_deref(), Some("gRPC response body too large"));
+    }
+}
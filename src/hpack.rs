@@ -0,0 +1,386 @@
+//! HPACK (RFC 7541) header compression for the HTTP/2 probes.
+//!
+//! Requests are encoded with literal fields only, so no encoder state is
+//! kept; responses are decoded with the static and dynamic tables and the
+//! Huffman code.
+
+use std::collections::VecDeque;
+
+/// RFC 7541 Appendix A.
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Code length of each symbol (256 is EOS) in the canonical Huffman code of
+/// RFC 7541 Appendix B; codes are assigned in (length, symbol) order.
+#[rustfmt::skip]
+const HUFFMAN_LENGTHS: [u8; 257] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28,
+    28, 28, 28, 28, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    6, 10, 10, 12, 13, 6, 8, 11, 10, 10, 8, 11, 8, 6, 6, 6,
+    5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 7, 8, 15, 6, 12, 10,
+    13, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 8, 7, 8, 13, 19, 13, 14, 6,
+    15, 5, 6, 5, 6, 5, 6, 6, 6, 5, 7, 7, 6, 6, 6, 5,
+    6, 7, 6, 5, 5, 6, 7, 7, 7, 7, 7, 15, 11, 14, 13, 28,
+    20, 22, 20, 20, 22, 22, 22, 23, 22, 23, 23, 23, 23, 23, 24, 23,
+    24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24,
+    22, 21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23,
+    21, 21, 22, 21, 23, 22, 23, 23, 20, 22, 22, 22, 23, 22, 22, 23,
+    26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25,
+    19, 21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27,
+    20, 24, 20, 21, 22, 21, 21, 23, 22, 22, 25, 25, 24, 24, 26, 23,
+    26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26,
+    30,
+];
+
+const EOS: u16 = 256;
+
+/// Largest dynamic table size a peer may use without a SETTINGS change.
+const DEFAULT_TABLE_SIZE: usize = 4096;
+
+/// Append a header field as a literal without indexing, new name.
+pub(crate) fn encode_literal(out: &mut Vec<u8>, name: &str, value: &str) {
+    out.push(0x00);
+    encode_string(out, name.as_bytes());
+    encode_string(out, value.as_bytes());
+}
+
+/// Append a header field as a literal without indexing, with the name taken
+/// from static table entry `index`.
+pub(crate) fn encode_indexed_name(out: &mut Vec<u8>, index: usize, value: &str) {
+    encode_integer(out, 0x00, 4, index);
+    encode_string(out, value.as_bytes());
+}
+
+/// Append a fully indexed static table field.
+pub(crate) fn encode_indexed(out: &mut Vec<u8>, index: usize) {
+    encode_integer(out, 0x80, 7, index);
+}
+
+fn encode_string(out: &mut Vec<u8>, value: &[u8]) {
+    encode_integer(out, 0x00, 7, value.len());
+    out.extend_from_slice(value);
+}
+
+/// RFC 7541 section 5.1 integer with an `prefix`-bit prefix after `flags`.
+fn encode_integer(out: &mut Vec<u8>, flags: u8, prefix: u32, value: usize) {
+    let max = (1 << prefix) - 1;
+    if value < max {
+        out.push(flags | value as u8);
+        return;
+    }
+    out.push(flags | max as u8);
+    let mut rest = value - max;
+    while rest >= 0x80 {
+        out.push((rest as u8 & 0x7f) | 0x80);
+        rest >>= 7;
+    }
+    out.push(rest as u8);
+}
+
+/// Header block decoder; the dynamic table persists across blocks of one
+/// connection.
+#[derive(Debug)]
+pub(crate) struct Decoder {
+    table: VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self {
+            table: VecDeque::new(),
+            size: 0,
+            max_size: DEFAULT_TABLE_SIZE,
+        }
+    }
+}
+
+impl Decoder {
+    /// Decode one complete header block.
+    pub(crate) fn decode(&mut self, mut block: &[u8]) -> Result<Vec<(String, String)>, String> {
+        let mut fields = Vec::new();
+        while let Some(&first) = block.first() {
+            if first & 0x80 != 0 {
+                let index = integer(&mut block, 7)?;
+                fields.push(self.entry(index)?);
+            } else if first & 0x40 != 0 {
+                let field = self.literal(&mut block, 6)?;
+                self.insert(field.clone());
+                fields.push(field);
+            } else if first & 0x20 != 0 {
+                let size = integer(&mut block, 5)?;
+                if size > DEFAULT_TABLE_SIZE {
+                    return Err("HPACK table size update above the limit".into());
+                }
+                self.max_size = size;
+                self.evict();
+            } else {
+                fields.push(self.literal(&mut block, 4)?);
+            }
+        }
+        Ok(fields)
+    }
+
+    fn literal(&self, block: &mut &[u8], prefix: u32) -> Result<(String, String), String> {
+        let name = match integer(block, prefix)? {
+            0 => string(block)?,
+            index => self.entry(index)?.0,
+        };
+        Ok((name, string(block)?))
+    }
+
+    fn entry(&self, index: usize) -> Result<(String, String), String> {
+        let invalid = || format!("invalid HPACK table index {index}");
+        match index {
+            0 => Err(invalid()),
+            1..=61 => {
+                let (name, value) = STATIC_TABLE[index - 1];
+                Ok((name.into(), value.into()))
+            }
+            _ => self.table.get(index - 62).cloned().ok_or_else(invalid),
+        }
+    }
+
+    fn insert(&mut self, field: (String, String)) {
+        self.size += entry_size(&field);
+        self.table.push_front(field);
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size
+            && let Some(field) = self.table.pop_back()
+        {
+            self.size -= entry_size(&field);
+        }
+    }
+}
+
+fn entry_size((name, value): &(String, String)) -> usize {
+    name.len() + value.len() + 32
+}
+
+fn integer(block: &mut &[u8], prefix: u32) -> Result<usize, String> {
+    let truncated = || "truncated HPACK integer".to_string();
+    let (&first, rest) = block.split_first().ok_or_else(truncated)?;
+    *block = rest;
+
+    let max = (1_usize << prefix) - 1;
+    let mut value = usize::from(first) & max;
+    if value < max {
+        return Ok(value);
+    }
+    for shift in (0..28).step_by(7) {
+        let (&byte, rest) = block.split_first().ok_or_else(truncated)?;
+        *block = rest;
+        value += usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("HPACK integer too large".into())
+}
+
+fn string(block: &mut &[u8]) -> Result<String, String> {
+    let huffman = block.first().is_some_and(|&byte| byte & 0x80 != 0);
+    let len = integer(block, 7)?;
+    if block.len() < len {
+        return Err("truncated HPACK string".into());
+    }
+    let (raw, rest) = block.split_at(len);
+    *block = rest;
+
+    let bytes = if huffman {
+        huffman_decode(raw)?
+    } else {
+        raw.to_vec()
+    };
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Decode a Huffman-coded string with canonical decoding over
+/// [HUFFMAN_LENGTHS].
+fn huffman_decode(input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut symbols: Vec<u16> = (0..=EOS).collect();
+    symbols.sort_by_key(|&symbol| (HUFFMAN_LENGTHS[usize::from(symbol)], symbol));
+    let mut counts = [0_u32; 31];
+    for &len in &HUFFMAN_LENGTHS {
+        counts[usize::from(len)] += 1;
+    }
+
+    let mut out = Vec::with_capacity(input.len() * 8 / 5);
+    // Code read so far, its length, the first code of that length and the
+    // index of that code's symbol.
+    let (mut code, mut len, mut first, mut index) = (0_u32, 0, 0_u32, 0_u32);
+    let mut all_ones = true;
+    for byte in input {
+        for bit in (0..8).rev().map(|shift| u32::from(byte >> shift) & 1) {
+            code = (code << 1) | bit;
+            all_ones &= bit == 1;
+            len += 1;
+            let count = counts[len];
+            if code - first < count {
+                let symbol = symbols[(index + code - first) as usize];
+                if symbol == EOS {
+                    return Err("Huffman string contains EOS".into());
+                }
+                out.push(symbol as u8);
+                (code, len, first, index, all_ones) = (0, 0, 0, 0, true);
+            } else {
+                index += count;
+                first = (first + count) << 1;
+                if len == 30 {
+                    return Err("invalid Huffman code".into());
+                }
+            }
+        }
+    }
+    // Padding is the most significant bits of EOS: at most seven ones.
+    if len > 7 || !all_ones {
+        return Err("invalid Huffman padding".into());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        let digits: Vec<u8> = text.bytes().filter(u8::is_ascii_hexdigit).collect();
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn huffman_code_is_complete() {
+        let kraft: f64 = HUFFMAN_LENGTHS
+            .iter()
+            .map(|&len| 0.5_f64.powi(i32::from(len)))
+            .sum();
+        assert_eq!(kraft, 1.0);
+    }
+
+    #[test]
+    fn decodes_rfc_huffman_examples() {
+        // RFC 7541 C.4 and C.6.
+        for (encoded, decoded) in [
+            ("f1e3 c2e5 f23a 6ba0 ab90 f4ff", "www.example.com"),
+            ("a8eb 1064 9cbf", "no-cache"),
+            ("25a8 49e9 5bb8 e8b4 bf", "custom-value"),
+            ("6402", "302"),
+            ("aec3 771a 4b", "private"),
+        ] {
+            assert_eq!(huffman_decode(&hex(encoded)).unwrap(), decoded.as_bytes());
+        }
+        assert!(huffman_decode(&[0x00]).is_err());
+        assert!(huffman_decode(&[0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+
+    #[test]
+    fn decodes_rfc_request_sequence() {
+        // RFC 7541 C.4.1 and C.4.2: the second block reuses the dynamic table.
+        let mut decoder = Decoder::default();
+        let first = decoder
+            .decode(&hex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff"))
+            .unwrap();
+        assert_eq!(
+            first,
+            [
+                (":method".to_string(), "GET".to_string()),
+                (":scheme".into(), "http".into()),
+                (":path".into(), "/".into()),
+                (":authority".into(), "www.example.com".into()),
+            ]
+        );
+        let second = decoder
+            .decode(&hex("8286 84be 5886 a8eb 1064 9cbf"))
+            .unwrap();
+        assert_eq!(second[3], (":authority".into(), "www.example.com".into()));
+        assert_eq!(second[4], ("cache-control".into(), "no-cache".into()));
+        assert_eq!(decoder.size, 110);
+
+        assert!(decoder.decode(&[0x80]).is_err());
+        assert!(decoder.decode(&[0xff, 0x80]).is_err());
+    }
+
+    #[test]
+    fn literals_round_trip() {
+        let mut block = Vec::new();
+        encode_indexed(&mut block, 3);
+        encode_indexed_name(&mut block, 4, "/grpc.health.v1.Health/Check");
+        encode_literal(&mut block, "te", "trailers");
+        encode_literal(&mut block, "x-long", &"v".repeat(300));
+
+        let fields = Decoder::default().decode(&block).unwrap();
+        assert_eq!(fields[0], (":method".into(), "POST".into()));
+        assert_eq!(fields[1].1, "/grpc.health.v1.Health/Check");
+        assert_eq!(fields[2], ("te".into(), "trailers".into()));
+        assert_eq!(fields[3].1.len(), 300);
+    }
+}
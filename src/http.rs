@@ -0,0 +1,477 @@
+//! Minimal HTTP/1.1 request run on a probe connection after connect (and
+//! after the TLS handshake for `https`).
+//!
+//! One request is sent with `Connection: close`; the response body is read to
+//! its end (Content-Length, chunked, or connection close) and discarded.
+
+use crate::{
+    error::{Result, TcpingError},
+    probe::ProbeOutcome,
+    wire::Wire,
+};
+use socket2::Socket;
+use std::{
+    io::{Read, Write},
+    ops::RangeInclusive,
+    time::Duration,
+};
+
+/// Longest response head line accepted.
+const MAX_HEAD: usize = 64 * 1024;
+
+const INCOMPLETE: &str = "connection closed before the response was complete";
+
+/// Timing and status of an HTTP probe request.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HttpInfo {
+    /// Final (non-1xx) status code.
+    pub status: Option<u16>,
+    /// Time from sending the request to the first response byte.
+    pub ttfb_ms: Option<f64>,
+    /// Time from sending the request to the end of the response body.
+    pub total_ms: f64,
+    pub body_bytes: u64,
+    /// Why no complete response was read.
+    pub error: Option<String>,
+}
+
+/// Request to send and the statuses that count as success.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct HttpConfig {
+    /// `https` URL: run the request over TLS.
+    pub(crate) tls: bool,
+    pub(crate) method: String,
+    /// Host header; the target (and non-default port) when unset.
+    pub(crate) host: Option<String>,
+    pub(crate) path: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) expect: StatusMatch,
+}
+
+impl HttpConfig {
+    /// `http://host/path`, `https://host/path` (the host only sets the Host
+    /// header; the probe still connects to the target), or a bare `/path`.
+    pub(crate) fn parse(url: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| TcpingError::InvalidOptions(format!("HTTP URL `{url}`: {reason}"));
+
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, Some(rest))
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, Some(rest))
+        } else {
+            (false, None)
+        };
+        let (host, path) = match rest {
+            Some(rest) => {
+                let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+                if authority.contains('@') {
+                    return Err(invalid("credentials are not supported"));
+                }
+                let path = if path.is_empty() { "/" } else { path };
+                ((!authority.is_empty()).then(|| authority.to_string()), path)
+            }
+            None if url.starts_with('/') => (None, url),
+            None => return Err(invalid("expected http://, https:// or a /path")),
+        };
+        if path
+            .bytes()
+            .any(|byte| byte.is_ascii_whitespace() || byte.is_ascii_control())
+            || host
+                .as_deref()
+                .is_some_and(|host| host.bytes().any(|byte| !byte.is_ascii_graphic()))
+        {
+            return Err(invalid("contains whitespace or control characters"));
+        }
+
+        Ok(Self {
+            tls,
+            method: "GET".into(),
+            host,
+            path: path.to_string(),
+            headers: Vec::new(),
+            expect: StatusMatch::default(),
+        })
+    }
+
+    /// TLS server name from the URL host, without port or brackets.
+    pub(crate) fn server_name(&self) -> Option<String> {
+        let host = self.host.as_deref()?;
+        let name = match host.strip_prefix('[') {
+            Some(rest) => rest.split(']').next().unwrap_or_default(),
+            None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+        };
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    /// Reject request parts that would corrupt the request head.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let is_token = |value: &str| {
+            !value.is_empty()
+                && value
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+        };
+
+        if !is_token(&self.method) {
+            return Err(TcpingError::InvalidOptions(format!(
+                "invalid HTTP method: {}",
+                self.method
+            )));
+        }
+        for (name, value) in &self.headers {
+            if !is_token(name) || value.bytes().any(|byte| byte == b'\r' || byte == b'\n') {
+                return Err(TcpingError::InvalidOptions(format!(
+                    "invalid HTTP header: {name}: {value}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&self) -> Vec<u8> {
+        let has = |name: &str| {
+            self.headers
+                .iter()
+                .any(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+
+        let mut head = format!("{} {} HTTP/1.1\r\n", self.method, self.path);
+        if !has("host")
+            && let Some(host) = &self.host
+        {
+            head.push_str(&format!("Host: {host}\r\n"));
+        }
+        if !has("user-agent") {
+            head.push_str(concat!(
+                "User-Agent: tcping/",
+                env!("CARGO_PKG_VERSION"),
+                "\r\n"
+            ));
+        }
+        if !has("accept") {
+            head.push_str("Accept: */*\r\n");
+        }
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        head.into_bytes()
+    }
+}
+
+/// Status codes that count as success; 200–399 by default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct StatusMatch(Vec<RangeInclusive<u16>>);
+
+impl Default for StatusMatch {
+    fn default() -> Self {
+        Self(vec![200..=399])
+    }
+}
+
+impl StatusMatch {
+    /// Comma-separated codes (`204`) and classes (`2xx`).
+    pub(crate) fn parse(spec: &str) -> Result<Self> {
+        let invalid = || {
+            TcpingError::InvalidOptions(format!(
+                "expected status `{spec}`: use codes like 200 or classes like 2xx"
+            ))
+        };
+
+        spec.split(',')
+            .map(|item| {
+                let item = item.trim();
+                if let Some(class) = item.strip_suffix("xx").or_else(|| item.strip_suffix("XX")) {
+                    let class: u16 = class.parse().map_err(|_| invalid())?;
+                    return (1..=5)
+                        .contains(&class)
+                        .then(|| class * 100..=class * 100 + 99)
+                        .ok_or_else(invalid);
+                }
+                let code: u16 = item.parse().map_err(|_| invalid())?;
+                (100..=599)
+                    .contains(&code)
+                    .then_some(code..=code)
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    fn matches(&self, status: u16) -> bool {
+        self.0.iter().any(|range| range.contains(&status))
+    }
+}
+
+/// Send the request on `io` and read the whole response.
+pub(crate) fn exchange<S: Read + Write>(
+    io: &mut S,
+    socket: &Socket,
+    config: &HttpConfig,
+    timeout: Duration,
+) -> (ProbeOutcome, HttpInfo) {
+    let mut response = Response {
+        wire: Wire::new(io, socket, timeout, MAX_HEAD).closed_with(INCOMPLETE),
+        body_bytes: 0,
+    };
+    let result = response.run(config);
+
+    let info = HttpInfo {
+        status: result.as_ref().ok().copied(),
+        ttfb_ms: response.wire.first_byte_ms(),
+        total_ms: response.wire.elapsed_ms(),
+        body_bytes: response.body_bytes,
+        error: result.as_ref().err().cloned(),
+    };
+    let outcome = match result {
+        Ok(status) if config.expect.matches(status) => ProbeOutcome::Open,
+        Ok(_) => ProbeOutcome::HttpStatusMismatch,
+        Err(_) => ProbeOutcome::HttpFailed,
+    };
+    (outcome, info)
+}
+
+/// Framing of a response body.
+enum Body {
+    Empty,
+    Length(u64),
+    Chunked,
+    UntilClose,
+}
+
+struct Response<'a, S> {
+    wire: Wire<'a, S>,
+    body_bytes: u64,
+}
+
+impl<S: Read + Write> Response<'_, S> {
+    /// Exchange one request; returns the final status code.
+    fn run(&mut self, config: &HttpConfig) -> std::result::Result<u16, String> {
+        self.wire.send(&config.request())?;
+
+        // Skip interim 1xx responses such as 100 Continue or 103 Early Hints.
+        let (status, headers) = loop {
+            let (status, headers) = self.head()?;
+            if !(100..=199).contains(&status) || status == 101 {
+                break (status, headers);
+            }
+        };
+
+        let header = |name: &str| {
+            headers
+                .iter()
+                .rev()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        let body =
+            if config.method.eq_ignore_ascii_case("HEAD") || matches!(status, 101 | 204 | 304) {
+                Body::Empty
+            } else if header("transfer-encoding")
+                .is_some_and(|value| value.to_ascii_lowercase().contains("chunked"))
+            {
+                Body::Chunked
+            } else if let Some(length) = header("content-length") {
+                Body::Length(
+                    length
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid Content-Length: {length}"))?,
+                )
+            } else {
+                Body::UntilClose
+            };
+
+        match body {
+            Body::Empty => {}
+            Body::Length(length) => self.skip(length)?,
+            Body::Chunked => self.skip_chunks()?,
+            Body::UntilClose => {
+                while self.wire.read_more()? > 0 {
+                    self.body_bytes += self.wire.pending.len() as u64;
+                    self.wire.pending.clear();
+                }
+            }
+        }
+        Ok(status)
+    }
+
+    /// Status code and headers of the next response head.
+    fn head(&mut self) -> std::result::Result<(u16, Vec<(String, String)>), String> {
+        let status_line = self.wire.line()?;
+        let status = status_line
+            .strip_prefix("HTTP/1.")
+            .and_then(|rest| rest.get(2..5))
+            .and_then(|code| code.parse::<u16>().ok())
+            .filter(|code| (100..=599).contains(code))
+            .ok_or_else(|| "malformed HTTP status line".to_string())?;
+
+        let mut headers = Vec::new();
+        loop {
+            let line = self.wire.line()?;
+            if line.is_empty() {
+                return Ok((status, headers));
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+    }
+
+    /// Discard `length` body bytes.
+    fn skip(&mut self, length: u64) -> std::result::Result<(), String> {
+        let mut left = length;
+        loop {
+            let take = left.min(self.wire.pending.len() as u64);
+            self.wire.pending.drain(..take as usize);
+            self.body_bytes += take;
+            left -= take;
+            if left == 0 {
+                return Ok(());
+            }
+            self.wire.fill()?;
+        }
+    }
+
+    fn skip_chunks(&mut self) -> std::result::Result<(), String> {
+        loop {
+            let line = self.wire.line()?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+            let size =
+                u64::from_str_radix(size, 16).map_err(|_| format!("invalid chunk size: {size}"))?;
+            if size == 0 {
+                // Trailer fields up to the final empty line.
+                while !self.wire.line()?.is_empty() {}
+                return Ok(());
+            }
+            self.skip(size)?;
+            self.wire.line()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_urls_and_paths() {
+        let config = HttpConfig::parse("https://api.example.com:8443/healthz?full=1").unwrap();
+        assert!(config.tls);
+        assert_eq!(config.host.as_deref(), Some("api.example.com:8443"));
+        assert_eq!(config.path, "/healthz?full=1");
+
+        assert_eq!(config.server_name().as_deref(), Some("api.example.com"));
+        let config = HttpConfig::parse("https://[::1]:8443/").unwrap();
+        assert_eq!(config.server_name().as_deref(), Some("::1"));
+
+        let config = HttpConfig::parse("http://").unwrap();
+        assert_eq!(
+            (config.tls, config.host, config.path.as_str()),
+            (false, None, "/")
+        );
+
+        assert_eq!(HttpConfig::parse("/status").unwrap().path, "/status");
+        for url in ["example.com/", "ftp://x/", "/a b", "http://user@host/"] {
+            assert!(HttpConfig::parse(url).is_err(), "{url}");
+        }
+    }
+
+    #[test]
+    fn request_head_has_defaults_and_extra_headers() {
+        let mut config = HttpConfig::parse("/healthz").unwrap();
+        config.host = Some("example.com".into());
+        config.method = "HEAD".into();
+        config.headers = vec![("Accept".into(), "application/json".into())];
+        let request = String::from_utf8(config.request()).unwrap();
+        assert_eq!(
+            request,
+            concat!(
+                "HEAD /healthz HTTP/1.1\r\nHost: example.com\r\nUser-Agent: tcping/",
+                env!("CARGO_PKG_VERSION"),
+                "\r\nAccept: application/json\r\nConnection: close\r\n\r\n"
+            )
+        );
+
+        config.headers = vec![("Bad Name".into(), "x".into())];
+        assert!(config.validate().is_err());
+        config.headers = vec![("X-Test".into(), "a\r\nInjected: 1".into())];
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn status_match_accepts_codes_and_classes() {
+        let expect = StatusMatch::parse("204, 3xx").unwrap();
+        assert!(expect.matches(204) && expect.matches(301));
+        assert!(!expect.matches(200));
+        assert!(StatusMatch::default().matches(302));
+        assert!(!StatusMatch::default().matches(404));
+        for spec in ["", "6xx", "abc", "99"] {
+            assert!(StatusMatch::parse(spec).is_err(), "{spec}");
+        }
+    }
+
+    fn serve(response: &'static [u8]) -> (Socket, std::thread::JoinHandle<Vec<u8>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response).unwrap();
+            request
+        });
+        let socket = Socket::from(std::net::TcpStream::connect(addr).unwrap());
+        (socket, server)
+    }
+
+    #[test]
+    fn reads_chunked_bodies_after_interim_responses() {
+        let (socket, server) = serve(
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\n",
+        );
+        let config = HttpConfig::parse("/").unwrap();
+        let (outcome, info) = exchange(&mut &socket, &socket, &config, Duration::from_secs(1));
+        assert!(server.join().unwrap().starts_with(b"GET / HTTP/1.1\r\n"));
+
+        assert_eq!(outcome, ProbeOutcome::Open);
+        assert_eq!(info.status, Some(200));
+        assert_eq!(info.body_bytes, 11);
+        assert!(info.ttfb_ms.is_some_and(|ttfb| ttfb <= info.total_ms));
+        assert_eq!(info.error, None);
+    }
+
+    #[test]
+    fn unexpected_status_fails_the_probe() {
+        let (socket, server) =
+            serve(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 4\r\n\r\nbusy");
+        let config = HttpConfig::parse("/").unwrap();
+        let (outcome, info) = exchange(&mut &socket, &socket, &config, Duration::from_secs(1));
+        server.join().unwrap();
+
+        assert_eq!(outcome, ProbeOutcome::HttpStatusMismatch);
+        assert_eq!((info.status, info.body_bytes), (Some(503), 4));
+    }
+
+    #[test]
+    fn truncated_response_is_an_http_failure() {
+        let (socket, server) = serve(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort");
+        let config = HttpConfig::parse("/").unwrap();
+        let (outcome, info) = exchange(&mut &socket, &socket, &config, Duration::from_secs(1));
+        server.join().unwrap();
+
+        assert_eq!(outcome, ProbeOutcome::HttpFailed);
+        assert_eq!(info.status, None);
+        assert_eq!(
+            info.error.as_deref(),
+            Some("connection closed before the response was complete")
+        );
+    }
+}
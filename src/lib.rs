@@ -5,18 +5,49 @@
 //! API.
 #![deny(unreachable_pub)]
 
+mod banner;
+mod dns;
+mod duration;
 mod error;
+mod grpc;
+mod hpack;
+mod http;
 mod probe;
+mod protocol;
+mod proxy;
+mod proxy_protocol;
+mod script;
 mod session;
+mod srv;
+mod starttls;
 mod stats;
 mod target;
+mod tcp_info;
 mod timestamp;
+mod tls;
+mod wire;
 
+pub use dns::DnsInfo;
+pub use duration::parse_duration;
 pub use error::{Result, TcpingError};
+pub use grpc::GrpcInfo;
+pub use http::HttpInfo;
+pub use probe::{CloseMode, ProbeOutcome, SocketOptions};
+pub use protocol::{Protocol, ProtocolInfo, ProtocolUrl};
+pub use proxy::{EnvProxy, Proxy, ProxyInfo, ProxyKind};
+pub use proxy_protocol::ProxyProtocol;
+pub use script::{Step, StepResult, parse_script};
 pub use session::{
     PingEvent, PingOptions, PingSession, ProbeCount, run_collect, run_collect_async,
     run_with_handler, run_with_handler_async, run_with_handler_until,
 };
-pub use stats::{OUTPUT_SCHEMA_V1, OUTPUT_SCHEMA_V2, PingResult, Summary, output_schema};
+pub use srv::{SrvRecord, resolve_srv, resolve_srv_with};
+pub use starttls::StartTls;
+pub use stats::{
+    OUTPUT_SCHEMA_V1, OUTPUT_SCHEMA_V2, PingResult, SourcePortStats, Summary, TcpInfoStats,
+    output_schema,
+};
 pub use target::{ResolvedTarget, Target, resolve_target};
+pub use tcp_info::TcpInfo;
 pub use timestamp::RecordTimestamp;
+pub use tls::{CertInfo, TlsInfo};
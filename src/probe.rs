@@ -1,13 +1,574 @@
 //! Blocking TCP connect wrapped in `spawn_blocking` for stable RTT.
 //!
-//! Using `std::net::TcpStream::connect_timeout` avoids async/IOCP
-//! scheduling jitter, while `spawn_blocking` keeps the async API.
+//! Using a blocking `connect` with timeout avoids async/IOCP scheduling
+//! jitter, while `spawn_blocking` keeps the async API. The socket is built
+//! with `socket2` so local binding can be applied before connect.
 
+use crate::{
+    banner::{self, BannerConfig},
+    dns::{self, DnsInfo, DnsQuery},
+    error::{Result, TcpingError},
+    grpc::{self, GrpcConfig, GrpcInfo},
+    http::{self, HttpConfig, HttpInfo},
+    protocol::{self, ProtocolConfig, ProtocolInfo},
+    proxy::{ProxyInfo, Tunnel},
+    proxy_protocol::ProxyHeader,
+    script::{self, Step, StepResult},
+    tcp_info::{self, TcpInfo},
+    tls::{self, TlsInfo, TlsSettings},
+};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 use std::{
-    net::{SocketAddr, TcpStream},
+    fmt, io,
+    io::Read,
+    net::{IpAddr, Shutdown, SocketAddr},
     time::{Duration, Instant},
 };
 
+/// IP-level options set on every probe socket before connect, so they
+/// already apply to the SYN.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SocketOptions {
+    /// IPv4 TTL or IPv6 hop limit.
+    pub ttl: Option<u32>,
+    /// IPv4 TOS or IPv6 traffic class byte; DSCP is its upper six bits.
+    pub tos: Option<u8>,
+    /// Linux `SO_MARK` (fwmark) used by policy routing rules.
+    pub mark: Option<u32>,
+}
+
+impl SocketOptions {
+    /// DSCP class carried in the TOS byte.
+    pub fn dscp(&self) -> Option<u8> {
+        self.tos.map(|tos| tos >> 2)
+    }
+
+    /// Whether no option is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn apply(&self, socket: &Socket, addr: SocketAddr) -> io::Result<()> {
+        if let Some(ttl) = self.ttl {
+            match addr {
+                SocketAddr::V4(_) => socket.set_ttl_v4(ttl)?,
+                SocketAddr::V6(_) => socket.set_unicast_hops_v6(ttl)?,
+            }
+        }
+
+        if let Some(tos) = self.tos {
+            match addr {
+                SocketAddr::V4(_) => set_tos_v4(socket, tos)?,
+                SocketAddr::V6(_) => set_tclass_v6(socket, tos)?,
+            }
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(mark) = self.mark {
+            socket.set_mark(mark)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "solaris", target_os = "illumos", target_os = "haiku")))]
+fn set_tos_v4(socket: &Socket, tos: u8) -> io::Result<()> {
+    socket.set_tos_v4(u32::from(tos))
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos", target_os = "haiku"))]
+fn set_tos_v4(_socket: &Socket, _tos: u8) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "IP_TOS is not supported on this platform",
+    ))
+}
+
+#[cfg(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "openbsd",
+))]
+fn set_tclass_v6(socket: &Socket, tclass: u8) -> io::Result<()> {
+    socket.set_tclass_v6(u32::from(tclass))
+}
+
+#[cfg(not(any(
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "openbsd",
+)))]
+fn set_tclass_v6(_socket: &Socket, _tclass: u8) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "IPV6_TCLASS is not supported on this platform",
+    ))
+}
+
+/// Classification of one probe.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ProbeOutcome {
+    /// Connected (and, with a hold window, stayed connected).
+    Open,
+    /// Connect failed or timed out.
+    Closed,
+    /// Connected, then the server closed the connection during the hold window.
+    OpenThenClosed,
+    /// Connected, then the server reset the connection during the hold window.
+    OpenThenReset,
+    /// Connected, but the banner did not match the expected pattern.
+    BannerMismatch,
+    /// Connected, but a send/expect step failed.
+    StepFailed,
+    /// Connected, but the plaintext STARTTLS exchange failed.
+    StartTlsFailed,
+    /// Connected, but the TLS handshake failed (timeout, protocol error, ...).
+    TlsFailed,
+    /// Connected, but the server rejected the TLS handshake with an alert,
+    /// e.g. a missing or untrusted client certificate.
+    TlsRejected,
+    /// Connected, but the server certificate was issued by an unknown CA.
+    TlsUnknownCa,
+    /// Connected, but the server certificate failed verification (expired,
+    /// wrong name, revoked, ...).
+    TlsVerifyFailed,
+    /// Connected, but the HTTP request got no complete response.
+    HttpFailed,
+    /// Connected, but the HTTP status was not one of the expected codes.
+    HttpStatusMismatch,
+    /// Connected, but the gRPC health check call failed.
+    GrpcFailed,
+    /// Connected, but the gRPC health check reported anything but `SERVING`.
+    GrpcNotServing,
+    /// Connected, but the application protocol probe got an error or an
+    /// unexpected reply.
+    ProtocolError,
+    /// Connected, but the DNS query got no valid response.
+    DnsFailed,
+    /// Connected, but the DNS response code was not one of the expected codes.
+    DnsRcodeMismatch,
+    /// Connected to the proxy, but it failed to open the tunnel (protocol
+    /// error, general failure, unexpected status).
+    ProxyFailed,
+    /// Connected to the proxy, but it required or rejected credentials.
+    ProxyAuthFailed,
+    /// Connected to the proxy, but its rules do not allow the target.
+    ProxyDenied,
+    /// Connected to the proxy, but it could not reach the target network or
+    /// host.
+    ProxyUnreachable,
+    /// Nothing was sent: the swept source port was in use or unavailable.
+    SourcePortBusy,
+}
+
+impl ProbeOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Closed => "closed",
+            Self::OpenThenClosed => "open_then_closed",
+            Self::OpenThenReset => "open_then_reset",
+            Self::BannerMismatch => "banner_mismatch",
+            Self::StepFailed => "step_failed",
+            Self::StartTlsFailed => "starttls_failed",
+            Self::TlsFailed => "tls_failed",
+            Self::TlsRejected => "tls_rejected",
+            Self::TlsUnknownCa => "tls_unknown_ca",
+            Self::TlsVerifyFailed => "tls_verify_failed",
+            Self::HttpFailed => "http_failed",
+            Self::HttpStatusMismatch => "http_status_mismatch",
+            Self::GrpcFailed => "grpc_failed",
+            Self::GrpcNotServing => "grpc_not_serving",
+            Self::ProtocolError => "protocol_error",
+            Self::DnsFailed => "dns_failed",
+            Self::DnsRcodeMismatch => "dns_rcode_mismatch",
+            Self::ProxyFailed => "proxy_failed",
+            Self::ProxyAuthFailed => "proxy_auth_failed",
+            Self::ProxyDenied => "proxy_denied",
+            Self::ProxyUnreachable => "proxy_unreachable",
+            Self::SourcePortBusy => "source_port_busy",
+        }
+    }
+
+    /// Whether the probe counts as a success.
+    pub fn is_success(&self) -> bool {
+        *self == Self::Open
+    }
+}
+
+impl fmt::Display for ProbeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Keep a connected socket open until `hold` after `start`, watching for the
+/// server closing it. Data the server sends is discarded. Returns the outcome
+/// and, if the server closed first, how long after `start` that happened.
+fn hold_open(socket: &Socket, start: Instant, hold: Duration) -> (ProbeOutcome, Option<f64>) {
+    let mut reader = socket;
+    let mut buf = [0; 512];
+
+    loop {
+        let remaining = hold.saturating_sub(start.elapsed());
+        if remaining.is_zero() || socket.set_read_timeout(Some(remaining)).is_err() {
+            return (ProbeOutcome::Open, None);
+        }
+
+        let outcome = match reader.read(&mut buf) {
+            Ok(0) => ProbeOutcome::OpenThenClosed,
+            Ok(_) => continue,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                ) =>
+            {
+                continue;
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted
+                ) =>
+            {
+                ProbeOutcome::OpenThenReset
+            }
+            Err(_) => ProbeOutcome::OpenThenClosed,
+        };
+        return (outcome, Some(start.elapsed().as_secs_f64() * 1_000.0));
+    }
+}
+
+/// How a probe connection is torn down after a successful connect.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CloseMode {
+    /// Orderly FIN close; the probing side keeps a TIME_WAIT entry.
+    #[default]
+    Fin,
+    /// Abortive close with `SO_LINGER=0`; the RST leaves no TIME_WAIT on
+    /// either side.
+    Rst,
+    /// Shut down the write half, then read until the server closes so it
+    /// sees a clean EOF. The probing side still ends in TIME_WAIT.
+    HalfClose,
+}
+
+impl CloseMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fin => "fin",
+            Self::Rst => "rst",
+            Self::HalfClose => "half_close",
+        }
+    }
+}
+
+impl fmt::Display for CloseMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Send FIN and wait (up to `timeout` in total) for the server's FIN, even
+/// if it keeps sending data.
+fn drain(socket: &Socket, timeout: Duration) {
+    if socket.shutdown(Shutdown::Write).is_err() {
+        return;
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut reader = socket;
+    let mut buf = [0; 512];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || socket.set_read_timeout(Some(remaining)).is_err() {
+            return;
+        }
+        if !matches!(reader.read(&mut buf), Ok(n) if n > 0) {
+            return;
+        }
+    }
+}
+
+/// Highest `TCP_SYNCNT` the Linux kernel accepts.
+const MAX_SYN_RETRIES: u8 = 127;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_syn_retries(socket: &Socket, retries: u8) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let value = libc::c_int::from(retries);
+    // SAFETY: `value` is a live `c_int` and the length matches its size.
+    let rc = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_SYNCNT,
+            (&value as *const libc::c_int).cast(),
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Whether a successful connect needed at least one retransmitted SYN.
+///
+/// Only the kernel retransmit counter is trusted: the wall-clock connect time
+/// cannot tell a lost SYN-ACK from a slow path, since the kernel RTT sample
+/// taken from a retransmitted SYN-ACK already includes the delay. Without
+/// `TCP_INFO` nothing is flagged.
+fn syn_retransmitted(info: Option<&TcpInfo>) -> bool {
+    info.is_some_and(|info| info.retransmits > 0)
+}
+
+/// Local binding and socket options applied to every probe socket before connect.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SocketConfig {
+    pub(crate) source_ip: Option<IpAddr>,
+    pub(crate) source_port: Option<u16>,
+    pub(crate) interface: Option<String>,
+    pub(crate) options: SocketOptions,
+    /// Linux `TCP_SYNCNT`: SYN retransmissions before connect gives up.
+    pub(crate) syn_retries: Option<u8>,
+    /// Linux `TCP_USER_TIMEOUT`: cap on unacknowledged data, SYNs included.
+    pub(crate) user_timeout: Option<Duration>,
+    pub(crate) close_mode: CloseMode,
+    /// Keep successful connections open this long, watching for EOF or RST.
+    pub(crate) hold: Option<Duration>,
+    /// Read a server banner after connect.
+    pub(crate) banner: Option<BannerConfig>,
+    /// Send/expect dialogue run after connect (and banner).
+    pub(crate) steps: Vec<Step>,
+    /// Tunnel opened through a SOCKS5 or HTTP proxy right after connect; the
+    /// probe address is then the proxy's.
+    pub(crate) tunnel: Option<Tunnel>,
+    /// PROXY protocol header sent right after connect (and tunnel), before
+    /// anything else.
+    pub(crate) proxy_protocol: Option<ProxyHeader>,
+    /// TLS handshake right after connect.
+    pub(crate) tls: Option<TlsSettings>,
+    /// HTTP request after connect (and TLS).
+    pub(crate) http: Option<HttpConfig>,
+    /// gRPC health check after connect (and TLS).
+    pub(crate) grpc: Option<GrpcConfig>,
+    /// Application protocol probe after connect (and TLS).
+    pub(crate) protocol: Option<ProtocolConfig>,
+    /// DNS query over the connection after connect (and TLS).
+    pub(crate) dns: Option<DnsQuery>,
+}
+
+impl SocketConfig {
+    /// Reject settings the current platform cannot apply.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.interface.is_some() && !cfg!(any(target_os = "linux", target_os = "android")) {
+            return Err(TcpingError::InvalidOptions(
+                "binding to an interface is only supported on Linux".into(),
+            ));
+        }
+
+        if self.options.mark.is_some() && !cfg!(any(target_os = "linux", target_os = "android")) {
+            return Err(TcpingError::InvalidOptions(
+                "SO_MARK is only supported on Linux".into(),
+            ));
+        }
+
+        if (self.syn_retries.is_some() || self.user_timeout.is_some())
+            && !cfg!(any(target_os = "linux", target_os = "android"))
+        {
+            return Err(TcpingError::InvalidOptions(
+                "TCP_SYNCNT and TCP_USER_TIMEOUT are only supported on Linux".into(),
+            ));
+        }
+
+        if matches!(self.syn_retries, Some(retries) if !(1..=MAX_SYN_RETRIES).contains(&retries)) {
+            return Err(TcpingError::InvalidOptions(format!(
+                "SYN retries must be between 1 and {MAX_SYN_RETRIES}"
+            )));
+        }
+
+        if self.user_timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return Err(TcpingError::InvalidOptions(
+                "TCP user timeout must be greater than zero".into(),
+            ));
+        }
+
+        if let Some(banner) = &self.banner
+            && (banner.max_bytes == 0 || banner.timeout.is_some_and(|timeout| timeout.is_zero()))
+        {
+            return Err(TcpingError::InvalidOptions(
+                "banner size and read timeout must be greater than zero".into(),
+            ));
+        }
+
+        if let Some(tls) = &self.tls {
+            tls.validate()?;
+            if self.banner.is_some() || !self.steps.is_empty() {
+                return Err(TcpingError::InvalidOptions(
+                    "banner and send/expect steps cannot be combined with TLS probes".into(),
+                ));
+            }
+        }
+
+        if let Some(http) = &self.http {
+            http.validate()?;
+        }
+        let requests = [
+            self.http.is_some(),
+            self.grpc.is_some(),
+            self.protocol.is_some(),
+            self.dns.is_some(),
+        ];
+        match requests.iter().filter(|&&set| set).count() {
+            0 => {}
+            1 if self.banner.is_none() && self.steps.is_empty() && self.hold.is_none() => {}
+            1 => {
+                return Err(TcpingError::InvalidOptions(
+                    "banner, send/expect steps and hold cannot be combined with HTTP, gRPC, protocol or DNS probes"
+                        .into(),
+                ));
+            }
+            _ => {
+                return Err(TcpingError::InvalidOptions(
+                    "only one of HTTP, gRPC health, protocol and DNS probes can be used".into(),
+                ));
+            }
+        }
+
+        if self.hold.is_some_and(|hold| hold.is_zero()) {
+            return Err(TcpingError::InvalidOptions(
+                "hold window must be greater than zero".into(),
+            ));
+        }
+
+        if matches!(self.options.ttl, Some(ttl) if !(1..=255).contains(&ttl)) {
+            return Err(TcpingError::InvalidOptions(
+                "TTL must be between 1 and 255".into(),
+            ));
+        }
+
+        if self.interface.as_deref() == Some("") {
+            return Err(TcpingError::InvalidOptions(
+                "interface name is empty".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Reject a source address whose family cannot reach `addr`.
+    pub(crate) fn check_family(&self, addr: SocketAddr) -> Result<()> {
+        match self.source_ip {
+            Some(ip) if ip.is_ipv4() != addr.is_ipv4() => Err(TcpingError::InvalidOptions(
+                format!("source address {ip} cannot reach {addr}: address families differ"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn local_addr(&self, addr: SocketAddr) -> Option<SocketAddr> {
+        if self.source_ip.is_none() && self.source_port.is_none() {
+            return None;
+        }
+
+        let ip = self.source_ip.unwrap_or(match addr {
+            SocketAddr::V4(_) => IpAddr::from([0, 0, 0, 0]),
+            SocketAddr::V6(_) => IpAddr::from([0_u16; 8]),
+        });
+        Some(SocketAddr::new(ip, self.source_port.unwrap_or(0)))
+    }
+
+    fn socket(&self, addr: SocketAddr) -> io::Result<Socket> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        self.options.apply(&socket, addr)?;
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            if let Some(interface) = &self.interface {
+                socket.bind_device(Some(interface.as_bytes()))?;
+            }
+            if let Some(retries) = self.syn_retries {
+                set_syn_retries(&socket, retries)?;
+            }
+            if let Some(timeout) = self.user_timeout {
+                socket.set_tcp_user_timeout(Some(timeout))?;
+            }
+        }
+
+        if self.close_mode == CloseMode::Rst {
+            socket.set_linger(Some(Duration::ZERO))?;
+        }
+
+        if let Some(local) = self.local_addr(addr) {
+            if self.source_port.is_some() {
+                // A pinned port is reused by the next probe.
+                socket.set_reuse_address(true)?;
+            }
+            socket.bind(&SockAddr::from(local))?;
+        }
+
+        Ok(socket)
+    }
+}
+
+/// Outcome of one connect attempt.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ProbeOutput {
+    pub(crate) success: bool,
+    pub(crate) outcome: ProbeOutcome,
+    pub(crate) rtt_ms: f64,
+    /// Time from connect until the server closed the connection while held.
+    pub(crate) time_to_close_ms: Option<f64>,
+    /// Time from connect to the first banner byte.
+    pub(crate) ttfb_ms: Option<f64>,
+    /// Sanitised server banner.
+    pub(crate) banner: Option<String>,
+    pub(crate) steps: Vec<StepResult>,
+    pub(crate) tls: Option<TlsInfo>,
+    pub(crate) http: Option<HttpInfo>,
+    pub(crate) grpc: Option<GrpcInfo>,
+    pub(crate) protocol: Option<ProtocolInfo>,
+    pub(crate) dns: Option<DnsInfo>,
+    pub(crate) proxy: Option<ProxyInfo>,
+    pub(crate) local_addr: Option<SocketAddr>,
+    pub(crate) tcp_info: Option<TcpInfo>,
+    /// Connected only after a SYN (or SYN-ACK) retransmission.
+    pub(crate) retransmitted: bool,
+    /// How the connection was closed; `None` when it never opened.
+    pub(crate) close_mode: Option<CloseMode>,
+}
+
+/// Time left until `deadline`, at least 1ms: a zero socket timeout is an
+/// error rather than an immediate timeout.
+fn remaining(deadline: Instant) -> Duration {
+    deadline
+        .saturating_duration_since(Instant::now())
+        .max(Duration::from_millis(1))
+}
+
 fn clamp_to_timeout_ms(elapsed_ms: f64, timeout: Duration) -> f64 {
     let max_ms = timeout.as_secs_f64() * 1_000.0;
     if elapsed_ms > max_ms {
@@ -19,17 +580,205 @@ fn clamp_to_timeout_ms(elapsed_ms: f64, timeout: Duration) -> f64 {
 
 /// Perform one TCP connect with timeout on a blocking thread.
 ///
-/// Returns `(success, rtt_ms)`.
-pub(crate) async fn probe_once(addr: SocketAddr, to: Duration) -> (bool, f64) {
-    tokio::task::spawn_blocking(move || {
+/// Failing to set up the local socket (for example binding an address that
+/// is not configured on this host) is an error rather than a failed probe.
+pub(crate) async fn probe_once(
+    addr: SocketAddr,
+    to: Duration,
+    config: SocketConfig,
+) -> Result<ProbeOutput> {
+    let output = tokio::task::spawn_blocking(move || {
+        let socket = config.socket(addr)?;
+
         let start = Instant::now();
-        let ok = TcpStream::connect_timeout(&addr, to).is_ok();
+        let ok = socket.connect_timeout(&SockAddr::from(addr), to).is_ok();
         let elapsed_ms = start.elapsed().as_secs_f64() * 1_000.0;
-        let rtt = clamp_to_timeout_ms(elapsed_ms, to);
-        (ok, rtt)
+        let local_addr = socket
+            .local_addr()
+            .ok()
+            .and_then(|local| local.as_socket())
+            .filter(|local| local.port() != 0);
+        let connected = Instant::now();
+        // Everything after connect shares one budget of `to`.
+        let mut deadline = connected + to;
+        let tcp_info = if ok { tcp_info::read(&socket) } else { None };
+        let retransmitted = ok && syn_retransmitted(tcp_info.as_ref());
+
+        let mut outcome = if ok {
+            ProbeOutcome::Open
+        } else {
+            ProbeOutcome::Closed
+        };
+        let mut time_to_close_ms = None;
+        let mut proxy_info = None;
+        if ok && let Some(tunnel) = &config.tunnel {
+            let (tunnel_outcome, info) = tunnel.open(&socket, remaining(deadline));
+            outcome = tunnel_outcome;
+            proxy_info = Some(info);
+        }
+        let peer = config
+            .tunnel
+            .as_ref()
+            .and_then(Tunnel::addr)
+            .unwrap_or(addr);
+        if outcome.is_success()
+            && let Some(header) = &config.proxy_protocol
+            && let Err(err) = header.send(&socket, local_addr, peer, remaining(deadline))
+        {
+            outcome = match err.kind() {
+                io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe => {
+                    ProbeOutcome::OpenThenReset
+                }
+                _ => ProbeOutcome::OpenThenClosed,
+            };
+        }
+        let mut tls_info = None;
+        let mut tls_conn = None;
+        if outcome.is_success()
+            && let Some(settings) = &config.tls
+        {
+            let (tls_outcome, info, conn) = tls::handshake(&socket, settings, remaining(deadline));
+            outcome = tls_outcome;
+            tls_info = Some(info);
+            tls_conn = conn;
+        }
+        let mut stream = tls::stream(tls_conn.as_mut(), &socket);
+        let mut http_info = None;
+        if outcome.is_success()
+            && let Some(http_config) = &config.http
+        {
+            let (http_outcome, info) =
+                http::exchange(&mut stream, &socket, http_config, remaining(deadline));
+            outcome = http_outcome;
+            http_info = Some(info);
+        }
+        let mut grpc_info = None;
+        if outcome.is_success()
+            && let Some(grpc_config) = &config.grpc
+        {
+            let tls = stream.is_tls();
+            let (grpc_outcome, info) =
+                grpc::check(&mut stream, &socket, grpc_config, tls, remaining(deadline));
+            outcome = grpc_outcome;
+            grpc_info = Some(info);
+        }
+        let mut protocol_info = None;
+        if outcome.is_success()
+            && let Some(protocol_config) = &config.protocol
+        {
+            let tls = stream.is_tls();
+            let (protocol_outcome, info) = protocol::run(
+                &mut stream,
+                &socket,
+                protocol_config,
+                tls,
+                remaining(deadline),
+            );
+            outcome = protocol_outcome;
+            protocol_info = Some(info);
+        }
+        let mut dns_info = None;
+        if outcome.is_success()
+            && let Some(query) = &config.dns
+        {
+            let (dns_outcome, info) =
+                dns::exchange(&mut stream, &socket, query, remaining(deadline));
+            outcome = dns_outcome;
+            dns_info = Some(info);
+        }
+        let mut grabbed = banner::Banner::default();
+        if outcome.is_success()
+            && let Some(banner_config) = &config.banner
+        {
+            grabbed = banner::read(
+                &socket,
+                banner_config.max_bytes,
+                banner_config
+                    .timeout
+                    .map_or(remaining(deadline), |timeout| {
+                        timeout.min(remaining(deadline))
+                    }),
+            );
+            outcome = grabbed.outcome(banner_config);
+            time_to_close_ms = grabbed.closed.map(|(_, ms)| ms);
+        }
+        let mut steps = Vec::new();
+        if outcome.is_success() && !config.steps.is_empty() {
+            steps = script::run(&socket, &config.steps, remaining(deadline));
+            if steps.iter().any(|step| !step.success) {
+                outcome = ProbeOutcome::StepFailed;
+            }
+        }
+        if outcome.is_success()
+            && let Some(hold) = config.hold
+        {
+            (outcome, time_to_close_ms) = hold_open(&socket, connected, hold);
+            // Holding is requested on top of the probe's own budget.
+            deadline += hold;
+        }
+        if outcome.is_success() && config.close_mode == CloseMode::HalfClose {
+            drain(&socket, remaining(deadline));
+        }
+
+        Ok::<_, io::Error>(ProbeOutput {
+            success: outcome.is_success(),
+            outcome,
+            rtt_ms: clamp_to_timeout_ms(elapsed_ms, to),
+            time_to_close_ms,
+            ttfb_ms: grabbed.ttfb_ms,
+            banner: grabbed.text,
+            steps,
+            tls: tls_info,
+            http: http_info,
+            grpc: grpc_info,
+            protocol: protocol_info,
+            dns: dns_info,
+            proxy: proxy_info,
+            local_addr,
+            tcp_info,
+            retransmitted,
+            close_mode: ok.then_some(config.close_mode),
+        })
     })
-    .await
-    .unwrap_or((false, to.as_secs_f64() * 1_000.0))
+    .await;
+
+    match output {
+        Ok(output) => Ok(output?),
+        Err(_) => Ok(ProbeOutput::failed(to.as_secs_f64() * 1_000.0, None)),
+    }
+}
+
+impl ProbeOutput {
+    /// A probe that never connected.
+    pub(crate) fn failed(rtt_ms: f64, local_addr: Option<SocketAddr>) -> Self {
+        Self {
+            success: false,
+            outcome: ProbeOutcome::Closed,
+            rtt_ms,
+            time_to_close_ms: None,
+            ttfb_ms: None,
+            banner: None,
+            steps: Vec::new(),
+            tls: None,
+            http: None,
+            grpc: None,
+            protocol: None,
+            dns: None,
+            proxy: None,
+            local_addr,
+            tcp_info: None,
+            retransmitted: false,
+            close_mode: None,
+        }
+    }
+
+    /// A probe that could not bind its swept source port.
+    pub(crate) fn source_port_busy(local_addr: Option<SocketAddr>) -> Self {
+        Self {
+            outcome: ProbeOutcome::SourcePortBusy,
+            ..Self::failed(0.0, local_addr)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -42,4 +791,237 @@ mod tests {
         assert_eq!(clamp_to_timeout_ms(1999.0, timeout), 1999.0);
         assert_eq!(clamp_to_timeout_ms(2005.0, timeout), 2000.0);
     }
+
+    #[test]
+    fn socket_options_are_applied_before_connect() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = SocketConfig {
+            options: SocketOptions {
+                ttl: Some(7),
+                tos: Some(46 << 2),
+                mark: None,
+            },
+            ..SocketConfig::default()
+        };
+
+        let socket = config.socket(addr).unwrap();
+        assert_eq!(socket.ttl_v4().unwrap(), 7);
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        assert_eq!(socket.tos_v4().unwrap(), 46 << 2);
+        socket
+            .connect_timeout(&SockAddr::from(addr), Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(config.options.dscp(), Some(46));
+    }
+
+    #[test]
+    fn syn_retransmits_are_detected_from_the_kernel_counter() {
+        let info = |rtt_ms, retransmits| TcpInfo {
+            rtt_ms,
+            rttvar_ms: rtt_ms / 2.0,
+            snd_mss: 1_448,
+            rcv_mss: 536,
+            retransmits,
+            snd_wscale: None,
+            rcv_wscale: None,
+        };
+
+        assert!(!syn_retransmitted(Some(&info(20.0, 0))));
+        assert!(syn_retransmitted(Some(&info(20.0, 1))));
+        // A slow path alone is not a retransmit.
+        assert!(!syn_retransmitted(Some(&info(1_150.0, 0))));
+        // Without kernel data nothing is flagged.
+        assert!(!syn_retransmitted(None));
+    }
+
+    #[test]
+    fn syn_control_is_linux_only_and_bounded() {
+        let config = SocketConfig {
+            syn_retries: Some(1),
+            user_timeout: Some(Duration::from_millis(1_500)),
+            ..SocketConfig::default()
+        };
+        assert_eq!(
+            config.validate().is_ok(),
+            cfg!(any(target_os = "linux", target_os = "android"))
+        );
+
+        let config = SocketConfig {
+            syn_retries: Some(MAX_SYN_RETRIES + 1),
+            ..SocketConfig::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = SocketConfig {
+            user_timeout: Some(Duration::ZERO),
+            ..SocketConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn syn_control_is_set_on_the_socket() {
+        let config = SocketConfig {
+            syn_retries: Some(2),
+            user_timeout: Some(Duration::from_millis(1_500)),
+            ..SocketConfig::default()
+        };
+        let socket = config.socket("127.0.0.1:80".parse().unwrap()).unwrap();
+        assert_eq!(
+            socket.tcp_user_timeout().unwrap(),
+            Some(Duration::from_millis(1_500))
+        );
+
+        use std::os::fd::AsRawFd;
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        // SAFETY: `value` and `len` are live and sized for a `c_int` option.
+        let rc = unsafe {
+            libc::getsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_SYNCNT,
+                (&mut value as *mut libc::c_int).cast(),
+                &mut len,
+            )
+        };
+        assert_eq!(rc, 0);
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn drain_gives_up_on_a_peer_that_keeps_sending() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            use std::io::Write;
+            let (mut stream, _) = listener.accept().unwrap();
+            while stream.write_all(&[0; 64]).is_ok() {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        let socket = SocketConfig::default().socket(addr).unwrap();
+        socket
+            .connect_timeout(&SockAddr::from(addr), Duration::from_secs(1))
+            .unwrap();
+        let start = Instant::now();
+        drain(&socket, Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        drop(socket);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn rst_close_sets_zero_linger() {
+        let config = SocketConfig {
+            close_mode: CloseMode::Rst,
+            ..SocketConfig::default()
+        };
+        let socket = config.socket("127.0.0.1:80".parse().unwrap()).unwrap();
+        assert_eq!(socket.linger().unwrap(), Some(Duration::ZERO));
+
+        let socket = SocketConfig::default()
+            .socket("127.0.0.1:80".parse().unwrap())
+            .unwrap();
+        assert_eq!(socket.linger().unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn half_close_waits_for_the_server_fin() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 16];
+            // EOF here means the client's FIN arrived before we close.
+            stream.read(&mut buf).unwrap()
+        });
+
+        let config = SocketConfig {
+            close_mode: CloseMode::HalfClose,
+            ..SocketConfig::default()
+        };
+        let output = probe_once(addr, Duration::from_secs(2), config)
+            .await
+            .unwrap();
+
+        assert!(output.success);
+        assert_eq!(output.close_mode, Some(CloseMode::HalfClose));
+        assert_eq!(server.join().unwrap(), 0);
+    }
+
+    async fn hold_against(
+        server: impl FnOnce(std::net::TcpStream) + Send + 'static,
+    ) -> ProbeOutput {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || server(listener.accept().unwrap().0));
+
+        let config = SocketConfig {
+            hold: Some(Duration::from_millis(300)),
+            ..SocketConfig::default()
+        };
+        let output = probe_once(addr, Duration::from_secs(1), config)
+            .await
+            .unwrap();
+        server.join().unwrap();
+        output
+    }
+
+    #[tokio::test]
+    async fn hold_classifies_how_the_server_ends_the_connection() {
+        let held = hold_against(|stream| {
+            std::thread::sleep(Duration::from_millis(400));
+            drop(stream);
+        })
+        .await;
+        assert_eq!(held.outcome, ProbeOutcome::Open);
+        assert!(held.success);
+        assert_eq!(held.time_to_close_ms, None);
+
+        let closed = hold_against(drop).await;
+        assert_eq!(closed.outcome, ProbeOutcome::OpenThenClosed);
+        assert!(!closed.success);
+        assert!(closed.time_to_close_ms.unwrap() < 300.0);
+
+        let reset = hold_against(|stream| {
+            // Let the client see the connect complete before the RST lands.
+            std::thread::sleep(Duration::from_millis(50));
+            socket2::SockRef::from(&stream)
+                .set_linger(Some(Duration::ZERO))
+                .unwrap();
+        })
+        .await;
+        assert_eq!(reset.outcome, ProbeOutcome::OpenThenReset);
+        assert!(reset.time_to_close_ms.is_some());
+    }
+
+    #[test]
+    fn source_family_must_match_target() {
+        let config = SocketConfig {
+            source_ip: Some("127.0.0.1".parse().unwrap()),
+            ..SocketConfig::default()
+        };
+        assert!(config.check_family("127.0.0.1:80".parse().unwrap()).is_ok());
+        assert!(config.check_family("[::1]:80".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn source_port_alone_binds_unspecified_address() {
+        let config = SocketConfig {
+            source_port: Some(40_000),
+            ..SocketConfig::default()
+        };
+        assert_eq!(
+            config.local_addr("[::1]:80".parse().unwrap()),
+            Some("[::]:40000".parse().unwrap())
+        );
+        assert_eq!(
+            SocketConfig::default().local_addr("[::1]:80".parse().unwrap()),
+            None
+        );
+    }
 }
@@ -0,0 +1,129 @@
+//! AMQP 0-9-1: send the protocol header and wait for `Connection.Start`,
+//! whose server properties name the broker and its version.
+
+use super::Wire;
+use crate::banner::sanitise;
+use std::io::{Read, Write};
+
+const PROTOCOL_HEADER: &[u8; 8] = b"AMQP\x00\x00\x09\x01";
+const FRAME_END: u8 = 0xce;
+
+pub(super) fn start<S: Read + Write>(wire: &mut Wire<'_, S>) -> Result<Option<String>, String> {
+    wire.send(PROTOCOL_HEADER)?;
+    let header = wire.exact(7)?;
+    // A broker that does not speak 0-9-1 answers with the header it wants.
+    if header.starts_with(b"AMQP") {
+        let rest = wire.exact(1)?;
+        let wanted = [&header[4..], &rest[..]].concat();
+        return Err(format!(
+            "server wants protocol AMQP {}-{}-{}",
+            wanted[1], wanted[2], wanted[3]
+        ));
+    }
+
+    let size = u32::from_be_bytes([header[3], header[4], header[5], header[6]]) as usize;
+    let payload = wire.exact(size)?;
+    let end = wire.exact(1)?[0];
+    // Method frame on channel 0: Connection (10) Start (10).
+    if header[..3] != [1, 0, 0] || end != FRAME_END || !payload.starts_with(&[0, 10, 0, 10]) {
+        return Err("unexpected frame instead of Connection.Start".into());
+    }
+    // Skip version major/minor, then read the server-properties table.
+    Ok(payload.get(6..).and_then(server_version))
+}
+
+/// `product version` from the server-properties field table.
+fn server_version(table: &[u8]) -> Option<String> {
+    let size = u32::from_be_bytes(table.get(..4)?.try_into().ok()?) as usize;
+    let mut fields = table.get(4..4 + size)?;
+    let (mut product, mut version) = (None, None);
+    while let Some((&len, rest)) = fields.split_first() {
+        let name = rest.get(..usize::from(len))?;
+        let (&kind, rest) = rest.get(usize::from(len)..)?.split_first()?;
+        let (value, rest) = field_value(kind, rest)?;
+        match (name, kind) {
+            (b"product", b'S') => product = Some(sanitise(value)),
+            (b"version", b'S') => version = Some(sanitise(value)),
+            _ => {}
+        }
+        fields = rest;
+    }
+    match (product, version) {
+        (Some(product), Some(version)) => Some(format!("{product} {version}")),
+        (product, version) => product.or(version),
+    }
+}
+
+/// Split one field value off `input`; long strings give their contents.
+fn field_value(kind: u8, input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let fixed = |len: usize| (input.len() >= len).then(|| input.split_at(len));
+    match kind {
+        b't' | b'b' | b'B' => fixed(1),
+        b'u' | b'U' | b's' => fixed(2),
+        b'i' | b'I' | b'f' => fixed(4),
+        b'D' => fixed(5),
+        b'l' | b'L' | b'd' | b'T' => fixed(8),
+        b'V' => fixed(0),
+        b'S' | b'x' | b'A' | b'F' => {
+            let len = u32::from_be_bytes(input.get(..4)?.try_into().ok()?) as usize;
+            let value = input.get(4..4 + len)?;
+            Some((value, &input[4 + len..]))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProtocolConfig, tests::*};
+    use super::*;
+    use crate::{Protocol, probe::ProbeOutcome};
+
+    fn connection_start() -> Vec<u8> {
+        let mut table = Vec::new();
+        for (name, kind, value) in [
+            (&b"capabilities"[..], b'F', &b"\x00\x00\x00\x00"[..]),
+            (b"product", b'S', b"RabbitMQ"),
+            (b"version", b'S', b"3.13.1"),
+        ] {
+            table.push(name.len() as u8);
+            table.extend_from_slice(name);
+            table.push(kind);
+            if kind == b'S' {
+                table.extend_from_slice(&(value.len() as u32).to_be_bytes());
+            }
+            table.extend_from_slice(value);
+        }
+        let mut payload = vec![0, 10, 0, 10, 0, 9];
+        payload.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        payload.extend(table);
+        payload.extend_from_slice(b"\x00\x00\x00\x05PLAIN\x00\x00\x00\x05en_US");
+
+        let mut frame = vec![1, 0, 0];
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend(payload);
+        frame.push(FRAME_END);
+        frame
+    }
+
+    #[test]
+    fn connection_start_names_the_broker() {
+        let (socket, server) = scripted_server(vec![(8, connection_start())]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Amqp));
+        assert_eq!(server.join().unwrap(), PROTOCOL_HEADER);
+        assert_eq!(outcome, ProbeOutcome::Open);
+        assert_eq!(info.server_version.as_deref(), Some("RabbitMQ 3.13.1"));
+    }
+
+    #[test]
+    fn other_protocol_versions_are_errors() {
+        let (socket, server) = scripted_server(vec![(8, b"AMQP\x01\x01\x00\x0a".to_vec())]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Amqp));
+        server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::ProtocolError);
+        assert_eq!(
+            info.error.as_deref(),
+            Some("server wants protocol AMQP 1-0-10")
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Kafka: `ApiVersions` v0, which every broker answers before
+//! authentication.
+
+use super::Wire;
+use std::io::{Read, Write};
+
+const API_VERSIONS: i16 = 18;
+const CORRELATION_ID: i32 = 0x7463_7067;
+const CLIENT_ID: &[u8] = b"tcping";
+
+pub(super) fn api_versions<S: Read + Write>(
+    wire: &mut Wire<'_, S>,
+) -> Result<Option<String>, String> {
+    wire.send(&request())?;
+    let size = wire.exact(4)?;
+    let size = i32::from_be_bytes([size[0], size[1], size[2], size[3]]);
+    let size = usize::try_from(size)
+        .ok()
+        .filter(|&size| size >= 6)
+        .ok_or("malformed response size")?;
+    let response = wire.exact(size)?;
+
+    if response[..4] != CORRELATION_ID.to_be_bytes() {
+        return Err("response for another request".into());
+    }
+    match i16::from_be_bytes([response[4], response[5]]) {
+        0 => Ok(None),
+        35 => Err("broker does not support ApiVersions v0".into()),
+        code => Err(format!("error code {code}")),
+    }
+}
+
+/// Request header v1 (api key, version 0, correlation id, client id) with an
+/// empty body.
+fn request() -> Vec<u8> {
+    let mut body = API_VERSIONS.to_be_bytes().to_vec();
+    body.extend_from_slice(&0_i16.to_be_bytes());
+    body.extend_from_slice(&CORRELATION_ID.to_be_bytes());
+    body.extend_from_slice(&(CLIENT_ID.len() as i16).to_be_bytes());
+    body.extend_from_slice(CLIENT_ID);
+
+    let mut out = (body.len() as i32).to_be_bytes().to_vec();
+    out.extend(body);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProtocolConfig, tests::*};
+    use super::*;
+    use crate::{Protocol, probe::ProbeOutcome};
+
+    fn response(error_code: i16) -> Vec<u8> {
+        let mut body = CORRELATION_ID.to_be_bytes().to_vec();
+        body.extend_from_slice(&error_code.to_be_bytes());
+        // One entry: ApiVersions 0..=3.
+        body.extend_from_slice(&[0, 0, 0, 1, 0, 18, 0, 0, 0, 3]);
+        let mut out = (body.len() as i32).to_be_bytes().to_vec();
+        out.extend(body);
+        out
+    }
+
+    #[test]
+    fn api_versions_response_decides_the_outcome() {
+        let (socket, server) = scripted_server(vec![(request().len(), response(0))]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Kafka));
+        assert_eq!(server.join().unwrap(), request());
+        assert_eq!((outcome, info.error), (ProbeOutcome::Open, None));
+
+        let (socket, server) = scripted_server(vec![(request().len(), response(35))]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Kafka));
+        server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::ProtocolError);
+        assert_eq!(
+            info.error.as_deref(),
+            Some("broker does not support ApiVersions v0")
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Memcached: `version` expecting `VERSION <version>`.
+
+use super::{Wire, unexpected};
+use crate::banner::sanitise;
+use std::io::{Read, Write};
+
+pub(super) fn version<S: Read + Write>(wire: &mut Wire<'_, S>) -> Result<Option<String>, String> {
+    wire.send(b"version\r\n")?;
+    let reply = wire.line()?;
+    match reply.strip_prefix("VERSION ") {
+        Some(version) => Ok(Some(sanitise(version.trim().as_bytes()))),
+        None if reply.starts_with("ERROR")
+            || reply.starts_with("CLIENT_ERROR")
+            || reply.starts_with("SERVER_ERROR") =>
+        {
+            Err(format!("version failed: {}", sanitise(reply.as_bytes())))
+        }
+        None => Err(unexpected(&reply)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProtocolConfig, tests::*};
+    use crate::{Protocol, probe::ProbeOutcome};
+
+    #[test]
+    fn version_is_reported() {
+        let (socket, server) = fake_server("", &[("version", "VERSION 1.6.21\r\n")]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Memcached));
+        assert_eq!(server.join().unwrap(), ["version"]);
+        assert_eq!(outcome, ProbeOutcome::Open);
+        assert_eq!(info.server_version.as_deref(), Some("1.6.21"));
+    }
+
+    #[test]
+    fn other_services_are_protocol_errors() {
+        let (socket, server) = fake_server("SSH-2.0-OpenSSH_9.6\r\n", &[]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Memcached));
+        server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::ProtocolError);
+        assert_eq!(
+            info.error.as_deref(),
+            Some("unexpected reply: SSH-2.0-OpenSSH_9.6")
+        );
+    }
+}
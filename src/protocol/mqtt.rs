@@ -0,0 +1,141 @@
+//! MQTT 3.1.1: `CONNECT` with a clean session, expecting `CONNACK` code 0,
+//! then `DISCONNECT`.
+
+use super::{ProtocolConfig, Wire};
+use std::{
+    io::{Read, Write},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// Brokers drop an existing session when a client id is reused, so each
+/// probe gets its own.
+static NEXT_CLIENT: AtomicU32 = AtomicU32::new(0);
+
+const DISCONNECT: [u8; 2] = [0xe0, 0x00];
+
+pub(super) fn connect<S: Read + Write>(
+    wire: &mut Wire<'_, S>,
+    config: &ProtocolConfig,
+) -> Result<Option<String>, String> {
+    let packet = connect_packet(
+        &client_id(NEXT_CLIENT.fetch_add(1, Ordering::Relaxed)),
+        config.username.as_deref(),
+        config.password.as_deref(),
+    )?;
+    wire.send(&packet)?;
+
+    let connack = wire.exact(4)?;
+    match connack[..] {
+        [0x20, 0x02, _, 0] => {
+            // The broker already answered; a failed goodbye changes nothing.
+            let _ = wire.send(&DISCONNECT);
+            Ok(None)
+        }
+        [0x20, 0x02, _, code] => Err(format!("connection refused: {}", refusal(code))),
+        _ => Err(format!("unexpected reply: {connack:02x?}")),
+    }
+}
+
+/// 22 alphanumeric characters: MQTT 3.1.1 brokers need only accept client
+/// ids of up to 23 characters from `0-9a-zA-Z`.
+fn client_id(n: u32) -> String {
+    format!("tcping{:08x}{n:08x}", std::process::id())
+}
+
+fn refusal(code: u8) -> String {
+    match code {
+        1 => "unacceptable protocol version".into(),
+        2 => "identifier rejected".into(),
+        3 => "server unavailable".into(),
+        4 => "bad user name or password".into(),
+        5 => "not authorized".into(),
+        code => format!("return code {code}"),
+    }
+}
+
+fn connect_packet(
+    client_id: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> Result<Vec<u8>, String> {
+    // Protocol name, level 4 (3.1.1), flags, keep alive 60 s.
+    let mut body = b"\x00\x04MQTT\x04\x00\x00\x3c".to_vec();
+    let mut flags = 0x02; // clean session
+    for (field, flag) in [(Some(client_id), 0), (username, 0x80), (password, 0x40)] {
+        if let Some(field) = field {
+            let len = u16::try_from(field.len()).map_err(|_| "credentials too long")?;
+            body.extend_from_slice(&len.to_be_bytes());
+            body.extend_from_slice(field.as_bytes());
+            flags |= flag;
+        }
+    }
+    // MQTT 3.1.1 only allows a password together with a user name.
+    if flags & 0xc0 == 0x40 {
+        return Err("MQTT passwords need a user name".into());
+    }
+    body[7] = flags;
+
+    let mut packet = vec![0x10];
+    let mut len = body.len();
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        packet.push(if len > 0 { byte | 0x80 } else { byte });
+        if len == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    Ok(packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProtocolConfig, tests::*};
+    use super::*;
+    use crate::{Protocol, probe::ProbeOutcome};
+
+    #[test]
+    fn connect_packet_carries_credentials() {
+        let packet = connect_packet("c", Some("u"), Some("pw")).unwrap();
+        assert_eq!(
+            packet,
+            b"\x10\x14\x00\x04MQTT\x04\xc2\x00\x3c\x00\x01c\x00\x01u\x00\x02pw"
+        );
+        assert!(connect_packet("c", None, Some("pw")).is_err());
+    }
+
+    #[test]
+    fn client_ids_fit_every_broker() {
+        for n in [0, u32::MAX] {
+            let id = client_id(n);
+            assert!(id.len() <= 23, "{id}");
+            assert!(id.bytes().all(|byte| byte.is_ascii_alphanumeric()), "{id}");
+        }
+        assert_ne!(client_id(1), client_id(2));
+    }
+
+    #[test]
+    fn connack_decides_the_outcome() {
+        let next_len = || {
+            let id = client_id(NEXT_CLIENT.load(Ordering::Relaxed));
+            connect_packet(&id, None, None).unwrap().len()
+        };
+        let (socket, server) = scripted_server(vec![
+            (next_len(), b"\x20\x02\x00\x00".to_vec()),
+            (DISCONNECT.len(), Vec::new()),
+        ]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Mqtt));
+        assert!(server.join().unwrap().ends_with(&DISCONNECT));
+        assert_eq!((outcome, info.error), (ProbeOutcome::Open, None));
+
+        let (socket, server) = scripted_server(vec![(next_len(), b"\x20\x02\x00\x05".to_vec())]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Mqtt));
+        server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::ProtocolError);
+        assert_eq!(
+            info.error.as_deref(),
+            Some("connection refused: not authorized")
+        );
+    }
+}
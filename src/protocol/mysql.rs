@@ -0,0 +1,81 @@
+//! MySQL: read the server's initial handshake packet for its version.
+
+use super::Wire;
+use crate::banner::sanitise;
+use std::io::{Read, Write};
+
+/// Version prefix MariaDB sends for old replication clients.
+const MARIADB_PREFIX: &str = "5.5.5-";
+
+pub(super) fn handshake<S: Read + Write>(wire: &mut Wire<'_, S>) -> Result<Option<String>, String> {
+    let header = wire.exact(4)?;
+    let len = usize::from(header[0]) | usize::from(header[1]) << 8 | usize::from(header[2]) << 16;
+    let payload = wire.exact(len)?;
+    parse(&payload).map(Some)
+}
+
+/// Server version from a HandshakeV10 payload, or the server's error packet.
+fn parse(payload: &[u8]) -> Result<String, String> {
+    match payload.split_first() {
+        Some((0x0a, rest)) => {
+            let version = rest
+                .split(|&byte| byte == 0)
+                .next()
+                .filter(|_| rest.contains(&0))
+                .ok_or("malformed handshake packet")?;
+            let version = sanitise(version);
+            Ok(match version.strip_prefix(MARIADB_PREFIX) {
+                Some(mariadb) if mariadb.contains("MariaDB") => mariadb.to_string(),
+                _ => version,
+            })
+        }
+        // ERR_Packet: code, optional `#` SQLSTATE, message.
+        Some((0xff, rest)) if rest.len() >= 2 => {
+            let code = u16::from_le_bytes([rest[0], rest[1]]);
+            let message = match &rest[2..] {
+                [b'#', message @ ..] if message.len() >= 5 => &message[5..],
+                message => message,
+            };
+            Err(format!("server error {code}: {}", sanitise(message)))
+        }
+        Some((version, _)) => Err(format!("unsupported handshake protocol {version}")),
+        None => Err("empty handshake packet".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProtocolConfig, tests::*};
+    use super::*;
+    use crate::{Protocol, probe::ProbeOutcome};
+
+    #[test]
+    fn handshake_reports_the_server_version() {
+        let payload = b"\x0a8.0.36\0\x08\0\0\0abcdefgh\0";
+        let mut packet = vec![payload.len() as u8, 0, 0, 0];
+        packet.extend_from_slice(payload);
+        let (socket, server) = scripted_server(vec![(0, packet)]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Mysql));
+        server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::Open);
+        assert_eq!(info.server_version.as_deref(), Some("8.0.36"));
+
+        assert_eq!(
+            parse(b"\x0a5.5.5-10.11.6-MariaDB-0+deb12u1\0"),
+            Ok("10.11.6-MariaDB-0+deb12u1".into())
+        );
+        assert!(parse(b"\x0a8.0.36").is_err());
+    }
+
+    #[test]
+    fn error_packets_fail_the_probe() {
+        assert_eq!(
+            parse(b"\xff\x10\x04Too many connections"),
+            Err("server error 1040: Too many connections".into())
+        );
+        assert_eq!(
+            parse(b"\xff\x6a\x04#HY000Host '10.0.0.9' is not allowed to connect"),
+            Err("server error 1130: Host '10.0.0.9' is not allowed to connect".into())
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! NATS: read the `INFO` greeting, then `CONNECT` and `PING` expecting `PONG`.
+
+use super::{ProtocolConfig, Wire, unexpected};
+use crate::banner::sanitise;
+use std::io::{Read, Write};
+
+pub(super) fn ping<S: Read + Write>(
+    wire: &mut Wire<'_, S>,
+    config: &ProtocolConfig,
+) -> Result<Option<String>, String> {
+    let greeting = wire.line()?;
+    let Some(info) = greeting.strip_prefix("INFO ") else {
+        return Err(unexpected(&greeting));
+    };
+    let version = json_string(info, "version").map(|version| sanitise(version.as_bytes()));
+
+    let mut connect = String::from(r#"{"verbose":false,"pedantic":false,"name":"tcping""#);
+    if let Some(user) = &config.username {
+        connect.push_str(&format!(r#","user":"{}""#, json_escape(user)));
+    }
+    if let Some(password) = &config.password {
+        // Without a user name the password is sent as a token.
+        let field = if config.username.is_some() {
+            "pass"
+        } else {
+            "auth_token"
+        };
+        connect.push_str(&format!(r#","{field}":"{}""#, json_escape(password)));
+    }
+    connect.push('}');
+    wire.send(format!("CONNECT {connect}\r\nPING\r\n").as_bytes())?;
+
+    loop {
+        let line = wire.line()?;
+        match line.as_str() {
+            "PONG" => return Ok(version),
+            "+OK" => {}
+            "PING" => wire.send(b"PONG\r\n")?,
+            _ if line.starts_with("INFO ") => {}
+            _ => {
+                return Err(match line.strip_prefix("-ERR ") {
+                    Some(error) => format!(
+                        "server error: {}",
+                        sanitise(error.trim_matches('\'').as_bytes())
+                    ),
+                    None => unexpected(&line),
+                });
+            }
+        }
+    }
+}
+
+/// Value of a top-level string field in the `INFO` JSON.
+fn json_string<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let start = json.find(&format!(r#""{key}":"#))? + key.len() + 3;
+    let value = json[start..].trim_start().strip_prefix('"')?;
+    value.split('"').next()
+}
+
+fn json_escape(value: &str) -> String {
+    value.chars().fold(String::new(), |mut out, ch| {
+        match ch {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            ch if ch.is_control() => out.push_str(&format!("\\u{:04x}", u32::from(ch))),
+            ch => out.push(ch),
+        }
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProtocolConfig, tests::*};
+    use crate::{Protocol, probe::ProbeOutcome};
+
+    const INFO: &str =
+        "INFO {\"server_id\":\"N1\",\"version\":\"2.10.4\",\"auth_required\":true}\r\n";
+
+    #[test]
+    fn info_version_is_reported() {
+        assert_eq!(
+            super::json_string(r#"{"a":1, "version": "2.10.4"}"#, "version"),
+            Some("2.10.4")
+        );
+        let (socket, server) = fake_server(INFO, &[("PING", "PONG\r\n")]);
+        let mut config = ProtocolConfig::new(Protocol::Nats);
+        config.username = Some("probe".into());
+        config.password = Some("se\"cret".into());
+        let (outcome, info) = probe(&socket, &config);
+        let lines = server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::Open);
+        assert_eq!(info.server_version.as_deref(), Some("2.10.4"));
+        assert_eq!(
+            lines[0],
+            r#"CONNECT {"verbose":false,"pedantic":false,"name":"tcping","user":"probe","pass":"se\"cret"}"#
+        );
+    }
+
+    #[test]
+    fn authorization_errors_fail_the_probe() {
+        let (socket, server) = fake_server(INFO, &[("PING", "-ERR 'Authorization Violation'\r\n")]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Nats));
+        server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::ProtocolError);
+        assert_eq!(
+            info.error.as_deref(),
+            Some("server error: Authorization Violation")
+        );
+    }
+}
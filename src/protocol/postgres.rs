@@ -0,0 +1,191 @@
+//! PostgreSQL: `SSLRequest`, then a startup message for user `tcping` and
+//! database `postgres`.
+//!
+//! The server asking for a password, or refusing the role, still proves the
+//! postmaster is answering; errors such as "the database system is starting
+//! up" fail the probe. The version is only reported under trust auth, as
+//! servers send it after authentication.
+
+use super::Wire;
+use crate::banner::sanitise;
+use std::io::{Read, Write};
+
+/// Protocol 1234.5679 in an 8-byte `SSLRequest`.
+const SSL_REQUEST: [u8; 8] = [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f];
+
+/// SQLSTATE classes that mean the server is up but refused the login:
+/// invalid authorization (`28`) and invalid catalog name (`3D`).
+const LOGIN_REFUSED: [&str; 2] = ["28", "3D"];
+
+pub(super) fn startup<S: Read + Write>(
+    wire: &mut Wire<'_, S>,
+    tls: bool,
+) -> Result<Option<String>, String> {
+    // Inside a TLS session (direct SSL) the upgrade request is not repeated.
+    if !tls {
+        wire.send(&SSL_REQUEST)?;
+        match wire.exact(1)?[0] {
+            // The server is waiting for a ClientHello: answering, but nothing
+            // more can be learnt in plaintext.
+            b'S' => return Ok(None),
+            b'N' => {}
+            b'E' => {
+                let (kind, body) = message_rest(wire, b'E')?;
+                return refused(kind, &body).map(|()| None);
+            }
+            other => return Err(format!("unexpected reply to SSLRequest: {other:#04x}")),
+        }
+    }
+
+    wire.send(&startup_message())?;
+    let mut version = None;
+    loop {
+        let (kind, body) = message(wire)?;
+        match kind {
+            b'R' => match body.get(..4) {
+                // AuthenticationOk (trust): wait for the parameters.
+                Some([0, 0, 0, 0]) => {}
+                Some(_) => return Ok(None),
+                None => return Err("malformed authentication request".into()),
+            },
+            b'S' => {
+                let mut fields = body.split(|&byte| byte == 0);
+                if fields.next() == Some(b"server_version") {
+                    version = fields.next().map(sanitise);
+                }
+            }
+            b'Z' => {
+                wire.send(b"X\0\0\0\x04")?;
+                return Ok(version);
+            }
+            b'E' => return refused(kind, &body).map(|()| None),
+            // Notices, backend key data, protocol version negotiation.
+            b'N' | b'K' | b'v' => {}
+            other => return Err(format!("unexpected message type {other:#04x}")),
+        }
+    }
+}
+
+/// StartupMessage for protocol 3.0, user `tcping`.
+fn startup_message() -> Vec<u8> {
+    let params: &[u8] = b"user\0tcping\0database\0postgres\0application_name\0tcping\0\0";
+    let len = (8 + params.len()) as u32;
+    let mut out = len.to_be_bytes().to_vec();
+    out.extend_from_slice(&0x0003_0000_u32.to_be_bytes());
+    out.extend_from_slice(params);
+    out
+}
+
+/// One backend message: type and body.
+fn message<S: Read + Write>(wire: &mut Wire<'_, S>) -> Result<(u8, Vec<u8>), String> {
+    let kind = wire.exact(1)?[0];
+    message_rest(wire, kind)
+}
+
+fn message_rest<S: Read + Write>(
+    wire: &mut Wire<'_, S>,
+    kind: u8,
+) -> Result<(u8, Vec<u8>), String> {
+    let header = wire.exact(4)?;
+    let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    if len < 4 {
+        return Err("malformed message length".into());
+    }
+    Ok((kind, wire.exact(len - 4)?))
+}
+
+/// `Ok` when an ErrorResponse only refuses the login.
+fn refused(kind: u8, body: &[u8]) -> Result<(), String> {
+    debug_assert_eq!(kind, b'E');
+    let mut code = None;
+    let mut text = None;
+    for field in body.split(|&byte| byte == 0) {
+        match field.split_first() {
+            Some((b'C', value)) => code = Some(sanitise(value)),
+            Some((b'M', value)) => text = Some(sanitise(value)),
+            _ => {}
+        }
+    }
+    match code {
+        Some(code) if LOGIN_REFUSED.iter().any(|class| code.starts_with(class)) => Ok(()),
+        code => Err(format!(
+            "server error {}: {}",
+            code.as_deref().unwrap_or("?"),
+            text.as_deref().unwrap_or("no message")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProtocolConfig, tests::*};
+    use super::*;
+    use crate::{Protocol, probe::ProbeOutcome};
+
+    fn backend(kind: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![kind];
+        out.extend_from_slice(&(body.len() as u32 + 4).to_be_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    #[test]
+    fn password_requests_count_as_answering() {
+        let startup = startup_message();
+        let (socket, server) = scripted_server(vec![
+            (8, b"N".to_vec()),
+            (
+                startup.len(),
+                backend(b'R', &[0, 0, 0, 10, b'S', b'C', b'R', b'A', b'M', 0, 0]),
+            ),
+        ]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Postgres));
+        assert_eq!(
+            [&SSL_REQUEST[..], &startup].concat(),
+            server.join().unwrap()
+        );
+        assert_eq!((outcome, info.error), (ProbeOutcome::Open, None));
+    }
+
+    #[test]
+    fn trust_auth_reports_the_server_version() {
+        let startup = startup_message();
+        let reply = [
+            backend(b'R', &[0, 0, 0, 0]),
+            backend(b'S', b"server_version\x0016.4\0"),
+            backend(b'Z', b"I"),
+        ]
+        .concat();
+        let (socket, server) = scripted_server(vec![
+            (8, b"N".to_vec()),
+            (startup.len(), reply),
+            (5, Vec::new()),
+        ]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Postgres));
+        assert!(server.join().unwrap().ends_with(b"X\0\0\0\x04"));
+        assert_eq!(outcome, ProbeOutcome::Open);
+        assert_eq!(info.server_version.as_deref(), Some("16.4"));
+    }
+
+    #[test]
+    fn startup_errors_fail_unless_the_login_was_refused() {
+        let no_role = backend(b'E', b"SFATAL\0C28000\0Mrole \"tcping\" does not exist\0\0");
+        assert_eq!(refused(b'E', &no_role[5..]), Ok(()));
+
+        let starting = backend(
+            b'E',
+            b"SFATAL\0C57P03\0Mthe database system is starting up\0\0",
+        );
+        let (socket, server) = scripted_server(vec![
+            (8, b"N".to_vec()),
+            (startup_message().len(), starting),
+        ]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Postgres));
+        server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::ProtocolError);
+        assert_eq!(
+            info.error.as_deref(),
+            Some("server error 57P03: the database system is starting up")
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Redis: `AUTH` when credentials are set, then `PING` expecting `+PONG`.
+
+use super::{ProtocolConfig, Wire, unexpected};
+use std::io::{Read, Write};
+
+pub(super) fn ping<S: Read + Write>(
+    wire: &mut Wire<'_, S>,
+    config: &ProtocolConfig,
+) -> Result<Option<String>, String> {
+    if let Some(password) = &config.password {
+        let mut auth = vec!["AUTH"];
+        auth.extend(config.username.as_deref());
+        auth.push(password);
+        wire.send(&command(&auth))?;
+        let reply = wire.line()?;
+        if reply != "+OK" {
+            return Err(refused("AUTH", &reply));
+        }
+    }
+
+    wire.send(&command(&["PING"]))?;
+    let reply = wire.line()?;
+    if reply != "+PONG" {
+        return Err(refused("PING", &reply));
+    }
+    Ok(None)
+}
+
+/// RESP array of bulk strings, so arguments may hold spaces.
+fn command(args: &[&str]) -> Vec<u8> {
+    let mut out = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        out.extend_from_slice(arg.as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+/// `-ERR ...` replies are the server's own error; anything else is garbage.
+fn refused(command: &str, reply: &str) -> String {
+    match reply.strip_prefix('-') {
+        Some(error) => format!(
+            "{command} failed: {}",
+            crate::banner::sanitise(error.as_bytes())
+        ),
+        None => unexpected(reply),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ProtocolConfig, tests::*};
+    use crate::{Protocol, probe::ProbeOutcome};
+
+    #[test]
+    fn commands_are_resp_arrays() {
+        assert_eq!(
+            super::command(&["AUTH", "a b"]),
+            b"*2\r\n$4\r\nAUTH\r\n$3\r\na b\r\n"
+        );
+    }
+
+    #[test]
+    fn auth_precedes_ping() {
+        let (socket, server) = fake_server("", &[("AUTH", "+OK\r\n"), ("PING", "+PONG\r\n")]);
+        let mut config = ProtocolConfig::new(Protocol::Redis);
+        config.username = Some("probe".into());
+        config.password = Some("secret".into());
+        let (outcome, info) = probe(&socket, &config);
+        assert_eq!(outcome, ProbeOutcome::Open);
+        assert_eq!(info.error, None);
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "*3", "$4", "AUTH", "$5", "probe", "$6", "secret", "*1", "$4", "PING"
+            ]
+        );
+    }
+
+    #[test]
+    fn server_errors_fail_the_probe() {
+        let (socket, server) = fake_server("", &[("PING", "-NOAUTH Authentication required.\r\n")]);
+        let (outcome, info) = probe(&socket, &ProtocolConfig::new(Protocol::Redis));
+        server.join().unwrap();
+        assert_eq!(outcome, ProbeOutcome::ProtocolError);
+        assert_eq!(
+            info.error.as_deref(),
+            Some("PING failed: NOAUTH Authentication required.")
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! PROXY protocol header sent right after connect, for backends behind
+//! HAProxy or a load balancer that drop connections without one.
+
+use socket2::Socket;
+use std::{
+    fmt,
+    io::{self, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+/// Version 2 signature, followed by version 2 with the PROXY command.
+const V2_SIGNATURE: &[u8; 13] = b"\r\n\r\n\0\r\nQUIT\n\x21";
+const V2_TCP4: u8 = 0x11;
+const V2_TCP6: u8 = 0x21;
+
+/// PROXY protocol version.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ProxyProtocol {
+    /// Human-readable `PROXY TCP4 ...` line.
+    V1,
+    /// Binary header.
+    V2,
+}
+
+impl ProxyProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::V1 => "v1",
+            Self::V2 => "v2",
+        }
+    }
+}
+
+impl fmt::Display for ProxyProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Header settings; unset addresses come from the probe connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ProxyHeader {
+    pub(crate) version: ProxyProtocol,
+    pub(crate) source: Option<SocketAddr>,
+    pub(crate) destination: Option<SocketAddr>,
+}
+
+impl ProxyHeader {
+    pub(crate) fn new(version: ProxyProtocol) -> Self {
+        Self {
+            version,
+            source: None,
+            destination: None,
+        }
+    }
+
+    /// Write the header for a connection from `local` to `peer`.
+    pub(crate) fn send(
+        &self,
+        socket: &Socket,
+        local: Option<SocketAddr>,
+        peer: SocketAddr,
+        timeout: Duration,
+    ) -> io::Result<()> {
+        let source = self
+            .source
+            .or(local)
+            .unwrap_or_else(|| SocketAddr::new(unspecified(peer.ip()), 0));
+        let destination = self.destination.unwrap_or(peer);
+        socket.set_write_timeout(Some(timeout))?;
+        let mut writer = socket;
+        writer.write_all(&self.encode(source, destination))
+    }
+
+    fn encode(&self, source: SocketAddr, destination: SocketAddr) -> Vec<u8> {
+        let (source, destination) = same_family(source, destination);
+        match self.version {
+            ProxyProtocol::V1 => {
+                let family = if source.is_ipv4() { "TCP4" } else { "TCP6" };
+                format!(
+                    "PROXY {family} {} {} {} {}\r\n",
+                    source.ip(),
+                    destination.ip(),
+                    source.port(),
+                    destination.port()
+                )
+                .into_bytes()
+            }
+            ProxyProtocol::V2 => {
+                let mut addresses = Vec::with_capacity(36);
+                let family = match (source.ip(), destination.ip()) {
+                    (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                        addresses.extend_from_slice(&src.octets());
+                        addresses.extend_from_slice(&dst.octets());
+                        V2_TCP4
+                    }
+                    (src, dst) => {
+                        addresses.extend_from_slice(&ipv6(src).octets());
+                        addresses.extend_from_slice(&ipv6(dst).octets());
+                        V2_TCP6
+                    }
+                };
+                addresses.extend_from_slice(&source.port().to_be_bytes());
+                addresses.extend_from_slice(&destination.port().to_be_bytes());
+
+                let mut header = V2_SIGNATURE.to_vec();
+                header.push(family);
+                header.extend_from_slice(&(addresses.len() as u16).to_be_bytes());
+                header.extend(addresses);
+                header
+            }
+        }
+    }
+}
+
+/// The header carries one address family, so a mixed pair is sent as IPv6
+/// with the IPv4 side mapped.
+fn same_family(source: SocketAddr, destination: SocketAddr) -> (SocketAddr, SocketAddr) {
+    if source.is_ipv4() == destination.is_ipv4() {
+        return (source, destination);
+    }
+    let mapped = |addr: SocketAddr| SocketAddr::new(IpAddr::V6(ipv6(addr.ip())), addr.port());
+    (mapped(source), mapped(destination))
+}
+
+fn ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn unspecified(like: IpAddr) -> IpAddr {
+    match like {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(addr: &str) -> SocketAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn v1_header_is_a_text_line() {
+        let header = ProxyHeader::new(ProxyProtocol::V1);
+        assert_eq!(
+            header.encode(addr("192.0.2.10:51000"), addr("198.51.100.1:443")),
+            b"PROXY TCP4 192.0.2.10 198.51.100.1 51000 443\r\n"
+        );
+        assert_eq!(
+            header.encode(addr("[2001:db8::1]:51000"), addr("[2001:db8::2]:80")),
+            b"PROXY TCP6 2001:db8::1 2001:db8::2 51000 80\r\n"
+        );
+    }
+
+    #[test]
+    fn v2_header_is_binary() {
+        let header = ProxyHeader::new(ProxyProtocol::V2);
+        let encoded = header.encode(addr("192.0.2.10:51000"), addr("198.51.100.1:443"));
+        assert_eq!(&encoded[..12], b"\r\n\r\n\0\r\nQUIT\n");
+        assert_eq!(
+            &encoded[12..],
+            &[
+                0x21, 0x11, 0, 12, 192, 0, 2, 10, 198, 51, 100, 1, 0xc7, 0x38, 0x01, 0xbb
+            ]
+        );
+
+        let encoded = header.encode(addr("192.0.2.10:1"), addr("[2001:db8::2]:2"));
+        assert_eq!((encoded[13], encoded[15]), (V2_TCP6, 36));
+        let mapped: Ipv6Addr = "::ffff:192.0.2.10".parse().unwrap();
+        assert_eq!(&encoded[16..32], &mapped.octets());
+    }
+}
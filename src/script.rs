@@ -0,0 +1,354 @@
+//! Send/expect dialogue run on a probe connection after connect.
+//!
+//! One step per line:
+//!
+//! ```text
+//! # comment
+//! send "PING\r\n"
+//! expect "+PONG" timeout=500ms
+//! send hex:0000000c
+//! expect hex:00ff
+//! ```
+//!
+//! Quoted payloads understand `\r`, `\n`, `\t`, `\0`, `\\`, `\"` and `\xNN`.
+//! `expect` succeeds when the next bytes received start with the payload.
+
+use crate::{
+    banner::sanitise,
+    duration::parse_duration,
+    error::{Result, TcpingError},
+};
+use socket2::Socket;
+use std::{
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+/// One step of a send/expect script.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Write these bytes.
+    Send {
+        data: Vec<u8>,
+        timeout: Option<Duration>,
+    },
+    /// Read until the received bytes start with `prefix`, or fail.
+    Expect {
+        prefix: Vec<u8>,
+        timeout: Option<Duration>,
+    },
+}
+
+impl Step {
+    /// Parse a single step line.
+    pub fn parse(line: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| TcpingError::InvalidOptions(format!("step `{line}`: {reason}"));
+
+        let line = line.trim();
+        let (verb, rest) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| invalid("expected `send <payload>` or `expect <payload>`"))?;
+        let (payload, rest) =
+            parse_payload(rest.trim_start()).map_err(|reason| invalid(&reason))?;
+
+        let timeout = match rest.trim() {
+            "" => None,
+            option => {
+                let value = option
+                    .strip_prefix("timeout=")
+                    .ok_or_else(|| invalid("only `timeout=<duration>` may follow the payload"))?;
+                let timeout = parse_duration(value).map_err(|err| match err {
+                    TcpingError::InvalidOptions(reason) => invalid(&reason),
+                    err => err,
+                })?;
+                Some(timeout)
+            }
+        };
+
+        match verb {
+            "send" => Ok(Self::Send {
+                data: payload,
+                timeout,
+            }),
+            "expect" if payload.is_empty() => Err(invalid("expected payload is empty")),
+            "expect" => Ok(Self::Expect {
+                prefix: payload,
+                timeout,
+            }),
+            _ => Err(invalid("unknown action; use `send` or `expect`")),
+        }
+    }
+
+    /// `send` or `expect`.
+    pub fn action(&self) -> &'static str {
+        match self {
+            Self::Send { .. } => "send",
+            Self::Expect { .. } => "expect",
+        }
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        match self {
+            Self::Send { timeout, .. } | Self::Expect { timeout, .. } => *timeout,
+        }
+    }
+}
+
+/// Parse a whole script: one step per line, blank lines and `#` comments skipped.
+pub fn parse_script(script: &str) -> Result<Vec<Step>> {
+    script
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Step::parse)
+        .collect()
+}
+
+/// `"quoted"` or `hex:...` payload followed by the rest of the line.
+fn parse_payload(input: &str) -> std::result::Result<(Vec<u8>, &str), String> {
+    if let Some(hex) = input.strip_prefix("hex:") {
+        let end = hex.find(char::is_whitespace).unwrap_or(hex.len());
+        return Ok((decode_hex(&hex[..end])?, &hex[end..]));
+    }
+
+    let quoted = input
+        .strip_prefix('"')
+        .ok_or("payload must be a \"quoted string\" or hex:...")?;
+    let mut out = Vec::new();
+    let mut chars = quoted.char_indices();
+    while let Some((index, ch)) = chars.next() {
+        match ch {
+            '"' => return Ok((out, &quoted[index + 1..])),
+            '\\' => {
+                let escaped = match chars.next().map(|(_, ch)| ch) {
+                    Some('r') => b'\r',
+                    Some('n') => b'\n',
+                    Some('t') => b'\t',
+                    Some('0') => 0,
+                    Some('\\') => b'\\',
+                    Some('"') => b'"',
+                    Some('x') => {
+                        let digits: String = chars.by_ref().take(2).map(|(_, ch)| ch).collect();
+                        decode_hex(&digits)?[0]
+                    }
+                    _ => return Err("unknown escape sequence".into()),
+                };
+                out.push(escaped);
+            }
+            ch => out.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Err("unterminated string".into())
+}
+
+fn decode_hex(hex: &str) -> std::result::Result<Vec<u8>, String> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return Err(format!("`{hex}` is not an even number of hex digits"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("`{hex}` is not valid hex"))
+        })
+        .collect()
+}
+
+/// Timing and result of one executed step.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StepResult {
+    /// `send` or `expect`.
+    pub action: &'static str,
+    pub success: bool,
+    pub duration_ms: f64,
+    /// Sanitised bytes an `expect` step saw.
+    pub received: Option<String>,
+    /// Why the step failed.
+    pub error: Option<String>,
+}
+
+/// Run `steps` on a connected socket, stopping at the first failed step.
+pub(crate) fn run(socket: &Socket, steps: &[Step], default_timeout: Duration) -> Vec<StepResult> {
+    let mut pending = Vec::new();
+    let mut results = Vec::with_capacity(steps.len());
+
+    for step in steps {
+        let timeout = step.timeout().unwrap_or(default_timeout);
+        let start = Instant::now();
+        let (received, outcome) = match step {
+            Step::Send { data, .. } => (None, send(socket, data, timeout)),
+            Step::Expect { prefix, .. } => {
+                let outcome = expect(socket, &mut pending, prefix, timeout);
+                let seen = &pending[..pending.len().min(prefix.len())];
+                let received = (!seen.is_empty()).then(|| sanitise(seen));
+                if outcome.is_ok() {
+                    pending.drain(..prefix.len());
+                }
+                (received, outcome)
+            }
+        };
+
+        let success = outcome.is_ok();
+        results.push(StepResult {
+            action: step.action(),
+            success,
+            duration_ms: start.elapsed().as_secs_f64() * 1_000.0,
+            received,
+            error: outcome.err(),
+        });
+        if !success {
+            break;
+        }
+    }
+    results
+}
+
+fn send(socket: &Socket, data: &[u8], timeout: Duration) -> std::result::Result<(), String> {
+    let mut writer = socket;
+    socket
+        .set_write_timeout(Some(timeout))
+        .and_then(|()| writer.write_all(data))
+        .map_err(|err| describe(&err))
+}
+
+fn expect(
+    socket: &Socket,
+    pending: &mut Vec<u8>,
+    prefix: &[u8],
+    timeout: Duration,
+) -> std::result::Result<(), String> {
+    let start = Instant::now();
+    let mut reader = socket;
+    let mut buf = [0; 512];
+
+    loop {
+        let seen = pending.len().min(prefix.len());
+        if pending[..seen] != prefix[..seen] {
+            return Err("unexpected response".into());
+        }
+        if seen == prefix.len() {
+            return Ok(());
+        }
+
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Err("timed out".into());
+        }
+        socket
+            .set_read_timeout(Some(remaining))
+            .map_err(|err| describe(&err))?;
+        match reader.read(&mut buf) {
+            Ok(0) => return Err("connection closed by server".into()),
+            Ok(n) => pending.extend_from_slice(&buf[..n]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(describe(&err)),
+        }
+    }
+}
+
+pub(crate) fn describe(err: &io::Error) -> String {
+    match err.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => "timed out".into(),
+        io::ErrorKind::ConnectionReset => "connection reset by server".into(),
+        _ => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_and_hex_payloads() {
+        assert_eq!(
+            Step::parse(r#"send "PING\r\n""#).unwrap(),
+            Step::Send {
+                data: b"PING\r\n".to_vec(),
+                timeout: None,
+            }
+        );
+        assert_eq!(
+            Step::parse("expect hex:00FF timeout=250ms").unwrap(),
+            Step::Expect {
+                prefix: vec![0x00, 0xff],
+                timeout: Some(Duration::from_millis(250)),
+            }
+        );
+        assert_eq!(
+            Step::parse(r#"expect "a \"b\" \x41" timeout=2s"#).unwrap(),
+            Step::Expect {
+                prefix: b"a \"b\" A".to_vec(),
+                timeout: Some(Duration::from_secs(2)),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_steps() {
+        for line in [
+            "send",
+            "receive \"x\"",
+            "send \"open",
+            "send hex:abc",
+            "send PING",
+            "expect \"\"",
+            "send \"x\" timeout=0s",
+            "send \"x\" retries=2",
+        ] {
+            assert!(Step::parse(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn step_timeouts_use_the_cli_duration_grammar() {
+        let timeout = |line| Step::parse(line).unwrap().timeout();
+        assert_eq!(
+            timeout("send \"x\" timeout=5"),
+            Some(Duration::from_millis(5))
+        );
+        assert_eq!(
+            timeout("send \"x\" timeout=1m"),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn script_skips_comments_and_blank_lines() {
+        let steps = parse_script("# redis\n\nsend \"PING\\r\\n\"\n  expect \"+PONG\"\n").unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[1].action(), "expect");
+    }
+
+    #[test]
+    fn run_stops_at_the_first_failed_step() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 6];
+            stream.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"PING\r\n");
+            stream.write_all(b"+PONG\r\n-ERR nope\r\n").unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+        });
+
+        let socket = Socket::from(std::net::TcpStream::connect(addr).unwrap());
+        let steps = parse_script(
+            "send \"PING\\r\\n\"\nexpect \"+PONG\\r\\n\"\nexpect \"+OK\"\nsend \"never\"",
+        )
+        .unwrap();
+        let results = run(&socket, &steps, Duration::from_secs(1));
+        server.join().unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].success && results[1].success);
+        assert_eq!(results[1].received.as_deref(), Some("+PONG"));
+        assert!(!results[2].success);
+        assert_eq!(results[2].received.as_deref(), Some("-ER"));
+        assert_eq!(results[2].error.as_deref(), Some("unexpected response"));
+    }
+}
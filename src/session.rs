@@ -1,19 +1,32 @@
 //! Session runner APIs for library callers.
 
 use crate::{
+    banner::{BannerConfig, BannerPattern},
+    dns::{self, DnsQuery},
     error::{Result, TcpingError},
-    probe::probe_once,
+    grpc::GrpcConfig,
+    http::{HttpConfig, StatusMatch},
+    probe::{CloseMode, ProbeOutput, SocketConfig, SocketOptions, probe_once},
+    protocol::{Protocol, ProtocolConfig},
+    proxy::{Proxy, Tunnel},
+    proxy_protocol::{ProxyHeader, ProxyProtocol},
+    script::Step,
+    starttls::StartTls,
     stats::{PingResult, Stats, Summary},
     target::{ResolvedTarget, Target, resolve_target},
     timestamp::RecordTimestamp,
+    tls::TlsSettings,
 };
 use std::{
     future::{Future, pending},
+    io,
+    net::{IpAddr, SocketAddr},
     num::NonZeroUsize,
     ops::ControlFlow,
+    path::PathBuf,
     time::Duration,
 };
-use tokio::time;
+use tokio::{task::JoinHandle, time};
 
 /// Number of probes to run in a session.
 #[non_exhaustive]
@@ -49,6 +62,12 @@ pub struct PingOptions {
     exit_on_success: bool,
     jitter: bool,
     timestamps: bool,
+    reverse_dns: bool,
+    socket: SocketConfig,
+    source_ports: Vec<u16>,
+    close_mode: Option<CloseMode>,
+    cert_warn_days: Option<u32>,
+    proxy: Option<Proxy>,
 }
 
 impl PingOptions {
@@ -62,6 +81,12 @@ impl PingOptions {
             exit_on_success: false,
             jitter: false,
             timestamps: false,
+            reverse_dns: false,
+            socket: SocketConfig::default(),
+            source_ports: Vec::new(),
+            close_mode: None,
+            cert_warn_days: None,
+            proxy: None,
         }
     }
 
@@ -100,6 +125,350 @@ impl PingOptions {
         self
     }
 
+    /// Annotate probes with the PTR name of the probed address. The lookup
+    /// runs in the background and never delays a probe.
+    pub fn reverse_dns(mut self, reverse_dns: bool) -> Self {
+        self.reverse_dns = reverse_dns;
+        self
+    }
+
+    /// Bind probe sockets to a local address before connecting.
+    pub fn with_source_addr(mut self, source: IpAddr) -> Self {
+        self.socket.source_ip = Some(source);
+        self
+    }
+
+    /// Bind probe sockets to a fixed local port before connecting.
+    pub fn with_source_port(mut self, port: u16) -> Self {
+        self.socket.source_port = Some(port);
+        self
+    }
+
+    /// Cycle successive probes through these local ports and break the
+    /// summary down per port. Useful for finding a single bad ECMP path.
+    pub fn with_source_port_sweep(mut self, ports: impl IntoIterator<Item = u16>) -> Self {
+        self.source_ports = ports.into_iter().collect();
+        self
+    }
+
+    /// Send probes out of a specific network interface (`SO_BINDTODEVICE`,
+    /// Linux only).
+    pub fn with_interface(mut self, interface: impl Into<String>) -> Self {
+        self.socket.interface = Some(interface.into());
+        self
+    }
+
+    /// IPv4 TTL / IPv6 hop limit for probe packets.
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.socket.options.ttl = Some(ttl);
+        self
+    }
+
+    /// DSCP class (0-63) for probe packets; shorthand for the TOS byte `dscp << 2`.
+    pub fn with_dscp(mut self, dscp: u8) -> Result<Self> {
+        if dscp > 63 {
+            return Err(TcpingError::InvalidOptions(
+                "DSCP must be between 0 and 63".into(),
+            ));
+        }
+        self.socket.options.tos = Some(dscp << 2);
+        Ok(self)
+    }
+
+    /// Raw IPv4 TOS / IPv6 traffic class byte for probe packets.
+    pub fn with_tos(mut self, tos: u8) -> Self {
+        self.socket.options.tos = Some(tos);
+        self
+    }
+
+    /// Linux fwmark (`SO_MARK`) for policy routing of probe packets.
+    pub fn with_mark(mut self, mark: u32) -> Self {
+        self.socket.options.mark = Some(mark);
+        self
+    }
+
+    /// Linux `TCP_SYNCNT`: how many times the kernel retransmits a probe's SYN
+    /// before the connect fails on its own.
+    pub fn with_syn_retries(mut self, retries: u8) -> Self {
+        self.socket.syn_retries = Some(retries);
+        self
+    }
+
+    /// Linux `TCP_USER_TIMEOUT` for probe sockets, which also bounds SYN
+    /// retransmission.
+    pub fn with_user_timeout(mut self, timeout: Duration) -> Self {
+        self.socket.user_timeout = Some(timeout);
+        self
+    }
+
+    /// Keep each successful connection open for `hold` and classify probes
+    /// the server closes or resets within the window as failures.
+    pub fn with_hold(mut self, hold: Duration) -> Self {
+        self.socket.hold = Some(hold);
+        self
+    }
+
+    /// Reach the target through a SOCKS5 or HTTP CONNECT proxy. Probes then
+    /// connect to the proxy, so the connect RTT is the time to reach it and
+    /// the tunnel setup is reported separately.
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Send a PROXY protocol header right after connect, before TLS, banner,
+    /// steps or hold. Addresses default to the probe connection's own.
+    pub fn with_proxy_protocol(mut self, version: ProxyProtocol) -> Self {
+        self.socket.proxy_protocol = Some(ProxyHeader::new(version));
+        self
+    }
+
+    /// Source address to claim in the PROXY header; needs
+    /// [`Self::with_proxy_protocol`].
+    pub fn with_proxy_source(mut self, source: SocketAddr) -> Result<Self> {
+        self.proxy_header_mut()?.source = Some(source);
+        Ok(self)
+    }
+
+    /// Destination address to claim in the PROXY header; needs
+    /// [`Self::with_proxy_protocol`].
+    pub fn with_proxy_destination(mut self, destination: SocketAddr) -> Result<Self> {
+        self.proxy_header_mut()?.destination = Some(destination);
+        Ok(self)
+    }
+
+    /// The PROXY header the address setters fill in.
+    fn proxy_header_mut(&mut self) -> Result<&mut ProxyHeader> {
+        self.socket.proxy_protocol.as_mut().ok_or_else(|| {
+            TcpingError::InvalidOptions("PROXY header addresses need the PROXY protocol".into())
+        })
+    }
+
+    /// Read the server's banner after connect (enabled with defaults: one line
+    /// of at most 256 bytes within the probe timeout).
+    pub fn read_banner(mut self, read_banner: bool) -> Self {
+        self.socket.banner = read_banner.then(BannerConfig::default);
+        self
+    }
+
+    /// Read at most `max_bytes` of banner; enables banner mode.
+    pub fn with_banner_limit(mut self, max_bytes: usize) -> Self {
+        self.socket
+            .banner
+            .get_or_insert_with(BannerConfig::default)
+            .max_bytes = max_bytes;
+        self
+    }
+
+    /// Banner read timeout instead of the probe timeout; enables banner mode.
+    pub fn with_banner_timeout(mut self, timeout: Duration) -> Self {
+        self.socket
+            .banner
+            .get_or_insert_with(BannerConfig::default)
+            .timeout = Some(timeout);
+        self
+    }
+
+    /// Fail probes whose banner does not match `pattern`; enables banner mode.
+    pub fn expect_banner(mut self, pattern: &str) -> Result<Self> {
+        let pattern = BannerPattern::new(pattern)
+            .map_err(|err| TcpingError::InvalidOptions(format!("invalid banner pattern: {err}")))?;
+        self.socket
+            .banner
+            .get_or_insert_with(BannerConfig::default)
+            .expect = Some(pattern);
+        Ok(self)
+    }
+
+    /// Run a send/expect dialogue after connect (and after the banner, if
+    /// read); a failed step fails the probe.
+    pub fn with_steps(mut self, steps: impl IntoIterator<Item = Step>) -> Self {
+        self.socket.steps = steps.into_iter().collect();
+        self
+    }
+
+    /// Complete a TLS handshake after connect and report its timing and
+    /// negotiated parameters (needs the `tls` feature).
+    pub fn tls(mut self, tls: bool) -> Self {
+        self.socket.tls = tls.then(TlsSettings::default);
+        self
+    }
+
+    /// TLS server name (SNI) instead of the target host; enables TLS.
+    pub fn with_tls_server_name(mut self, name: impl Into<String>) -> Self {
+        self.socket
+            .tls
+            .get_or_insert_with(TlsSettings::default)
+            .server_name = Some(name.into());
+        self
+    }
+
+    /// ALPN protocols to offer, e.g. `["h2", "http/1.1"]`; enables TLS.
+    pub fn with_alpn<S: Into<String>>(mut self, protocols: impl IntoIterator<Item = S>) -> Self {
+        self.socket
+            .tls
+            .get_or_insert_with(TlsSettings::default)
+            .alpn = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Skip server certificate verification; enables TLS.
+    pub fn insecure_tls(mut self, insecure: bool) -> Self {
+        self.socket
+            .tls
+            .get_or_insert_with(TlsSettings::default)
+            .insecure = insecure;
+        self
+    }
+
+    /// Present this PEM certificate chain and private key when the server
+    /// asks for client authentication (mTLS); enables TLS.
+    pub fn with_client_cert(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        let tls = self.socket.tls.get_or_insert_with(TlsSettings::default);
+        tls.client_cert = Some(cert.into());
+        tls.client_key = Some(key.into());
+        self
+    }
+
+    /// Verify the server against the CAs in this PEM bundle instead of the
+    /// built-in roots; enables TLS.
+    pub fn with_ca_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.socket
+            .tls
+            .get_or_insert_with(TlsSettings::default)
+            .ca_file = Some(path.into());
+        self
+    }
+
+    /// Upgrade a plaintext protocol with STARTTLS before the handshake;
+    /// enables TLS. The exchange is timed apart from the handshake.
+    pub fn with_starttls(mut self, protocol: StartTls) -> Self {
+        self.socket
+            .tls
+            .get_or_insert_with(TlsSettings::default)
+            .starttls = Some(protocol);
+        self
+    }
+
+    /// Fail the session when the server certificate expires within `days`;
+    /// enables TLS.
+    pub fn with_cert_warn_days(mut self, days: u32) -> Self {
+        self.socket.tls.get_or_insert_with(TlsSettings::default);
+        self.cert_warn_days = Some(days);
+        self
+    }
+
+    /// Send an HTTP request after connect and fail probes whose status is not
+    /// expected (200–399 unless set with [`Self::expect_status`]). `url` is a
+    /// bare `/path` or an `http(s)://host/path` URL; its host only sets the
+    /// Host header and SNI, and `https` enables TLS.
+    pub fn http_request(mut self, url: &str) -> Result<Self> {
+        let http = HttpConfig::parse(url)?;
+        if http.tls {
+            self.socket.tls.get_or_insert_with(TlsSettings::default);
+        }
+        self.socket.http = Some(http);
+        Ok(self)
+    }
+
+    /// HTTP request method, `GET` by default; needs [`Self::http_request`].
+    pub fn with_http_method(mut self, method: impl Into<String>) -> Result<Self> {
+        self.http_mut("an HTTP method")?.method = method.into();
+        Ok(self)
+    }
+
+    /// Extra HTTP request header, replacing a default of the same name;
+    /// needs [`Self::http_request`].
+    pub fn with_http_header(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<Self> {
+        self.http_mut("an HTTP header")?
+            .headers
+            .push((name.into(), value.into()));
+        Ok(self)
+    }
+
+    /// HTTP statuses that count as success, e.g. `200,204` or `2xx,301`;
+    /// needs [`Self::http_request`].
+    pub fn expect_status(mut self, spec: &str) -> Result<Self> {
+        let expect = StatusMatch::parse(spec)?;
+        self.http_mut("an expected status")?.expect = expect;
+        Ok(self)
+    }
+
+    /// The HTTP request that `what` configures.
+    fn http_mut(&mut self, what: &str) -> Result<&mut HttpConfig> {
+        self.socket
+            .http
+            .as_mut()
+            .ok_or_else(|| TcpingError::InvalidOptions(format!("{what} needs an HTTP request")))
+    }
+
+    /// Call the gRPC health checking protocol (`grpc.health.v1.Health/Check`)
+    /// for `service` (empty for the whole server) after connect, over h2c or,
+    /// with TLS enabled, over TLS; probes fail unless it reports `SERVING`.
+    pub fn grpc_health(mut self, service: impl Into<String>) -> Self {
+        self.socket.grpc = Some(GrpcConfig {
+            service: service.into(),
+            authority: None,
+        });
+        self
+    }
+
+    /// Speak `protocol` after connect (and TLS): Redis `PING`, Memcached
+    /// `version` or NATS `PING`; probes fail on protocol errors.
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.socket.protocol = Some(ProtocolConfig::new(protocol));
+        self
+    }
+
+    /// Send a DNS query for `name` and record type `qtype` (e.g. `A`, `AAAA`,
+    /// `TYPE65`) over each connection, plain or with TLS enabled;
+    /// probes fail unless the response code is `NOERROR`.
+    pub fn dns_query(mut self, name: &str, qtype: &str) -> Result<Self> {
+        self.socket.dns = Some(DnsQuery::new(name, qtype)?);
+        Ok(self)
+    }
+
+    /// Response codes that count as success for [`Self::dns_query`], e.g.
+    /// `NOERROR,NXDOMAIN`.
+    pub fn expect_dns_rcode(mut self, spec: &str) -> Result<Self> {
+        let expect = DnsQuery::parse_rcodes(spec)?;
+        let Some(dns) = self.socket.dns.as_mut() else {
+            return Err(TcpingError::InvalidOptions(
+                "expected response codes need a DNS query".into(),
+            ));
+        };
+        dns.expect = expect;
+        Ok(self)
+    }
+
+    /// Credentials for the protocol probe (Redis `AUTH`, NATS `CONNECT`);
+    /// needs [`Self::with_protocol`].
+    pub fn with_protocol_credentials(
+        mut self,
+        username: Option<String>,
+        password: impl Into<String>,
+    ) -> Result<Self> {
+        let Some(protocol) = self.socket.protocol.as_mut() else {
+            return Err(TcpingError::InvalidOptions(
+                "credentials need a protocol probe".into(),
+            ));
+        };
+        protocol.username = username;
+        protocol.password = Some(password.into());
+        protocol.validate()?;
+        Ok(self)
+    }
+
+    /// How successful probe connections are closed.
+    pub fn with_close_mode(mut self, close_mode: CloseMode) -> Self {
+        self.close_mode = Some(close_mode);
+        self
+    }
+
     pub fn target(&self) -> &Target {
         &self.target
     }
@@ -128,6 +497,114 @@ impl PingOptions {
         self.timestamps
     }
 
+    pub fn includes_reverse_dns(&self) -> bool {
+        self.reverse_dns
+    }
+
+    pub fn source_addr(&self) -> Option<IpAddr> {
+        self.socket.source_ip
+    }
+
+    pub fn source_port(&self) -> Option<u16> {
+        self.socket.source_port
+    }
+
+    pub fn source_port_sweep(&self) -> &[u16] {
+        &self.source_ports
+    }
+
+    pub fn interface(&self) -> Option<&str> {
+        self.socket.interface.as_deref()
+    }
+
+    pub fn socket_options(&self) -> SocketOptions {
+        self.socket.options
+    }
+
+    /// Close mode in effect: the explicit choice, otherwise RST when source
+    /// ports are pinned (the next probe reuses the port, which must not sit in
+    /// TIME_WAIT) and FIN in every other case.
+    pub fn close_mode(&self) -> CloseMode {
+        self.close_mode.unwrap_or(
+            if self.socket.source_port.is_some() || !self.source_ports.is_empty() {
+                CloseMode::Rst
+            } else {
+                CloseMode::Fin
+            },
+        )
+    }
+
+    pub fn reads_banner(&self) -> bool {
+        self.socket.banner.is_some()
+    }
+
+    pub fn banner_pattern(&self) -> Option<&str> {
+        self.socket
+            .banner
+            .as_ref()
+            .and_then(|banner| banner.expect.as_ref())
+            .map(BannerPattern::as_str)
+    }
+
+    pub fn uses_tls(&self) -> bool {
+        self.socket.tls.is_some()
+    }
+
+    pub fn starttls(&self) -> Option<StartTls> {
+        self.socket.tls.as_ref().and_then(|tls| tls.starttls)
+    }
+
+    pub fn cert_warn_days(&self) -> Option<u32> {
+        self.cert_warn_days
+    }
+
+    pub fn uses_http(&self) -> bool {
+        self.socket.http.is_some()
+    }
+
+    /// Service name of the gRPC health check, if one is configured.
+    pub fn grpc_service(&self) -> Option<&str> {
+        self.socket.grpc.as_ref().map(|grpc| grpc.service.as_str())
+    }
+
+    pub fn uses_dns(&self) -> bool {
+        self.socket.dns.is_some()
+    }
+
+    pub fn protocol(&self) -> Option<Protocol> {
+        self.socket
+            .protocol
+            .as_ref()
+            .map(|protocol| protocol.protocol)
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.socket.steps
+    }
+
+    pub fn proxy(&self) -> Option<&Proxy> {
+        self.proxy.as_ref()
+    }
+
+    pub fn proxy_protocol(&self) -> Option<ProxyProtocol> {
+        self.socket
+            .proxy_protocol
+            .as_ref()
+            .map(|header| header.version)
+    }
+
+    pub fn hold(&self) -> Option<Duration> {
+        self.socket.hold
+    }
+
+    pub fn syn_retries(&self) -> Option<u8> {
+        self.socket.syn_retries
+    }
+
+    pub fn user_timeout(&self) -> Option<Duration> {
+        self.socket.user_timeout
+    }
+
     fn validate(&self) -> Result<()> {
         if self.interval.is_zero() {
             return Err(TcpingError::InvalidOptions(
@@ -141,13 +618,31 @@ impl PingOptions {
             ));
         }
 
-        Ok(())
+        if self.socket.source_port == Some(0) || self.source_ports.contains(&0) {
+            return Err(TcpingError::InvalidOptions(
+                "source port must be between 1 and 65535".into(),
+            ));
+        }
+
+        if self.socket.source_port.is_some() && !self.source_ports.is_empty() {
+            return Err(TcpingError::InvalidOptions(
+                "a fixed source port cannot be combined with a source port sweep".into(),
+            ));
+        }
+
+        if let Some(proxy) = &self.proxy {
+            proxy.validate()?;
+        }
+
+        self.socket.validate()
     }
 }
 
 /// Event emitted during a probing session.
 #[non_exhaustive]
 #[derive(Clone, Debug, PartialEq)]
+// One event per probe; boxing the result would only cost callers a deref.
+#[allow(clippy::large_enum_variant)]
 pub enum PingEvent {
     Resolved(ResolvedTarget),
     Probe(PingResult),
@@ -219,6 +714,29 @@ where
     runtime()?.block_on(run_with_handler_async(options, handler))
 }
 
+/// SNI / certificate name for a target: the host name, or the bare address
+/// of an IP literal (no SNI is sent for those).
+fn tls_server_name(target: &Target) -> String {
+    match target.socket_addr_if_literal() {
+        Some(addr) => addr.ip().to_string(),
+        None => target.host().to_string(),
+    }
+}
+
+/// Host header or `:authority` for a target: the host (IPv6 literals in
+/// brackets) plus the port unless it is the scheme's default.
+fn http_host(target: &Target, tls: bool) -> String {
+    let host = match target.socket_addr_if_literal() {
+        Some(SocketAddr::V6(addr)) => format!("[{}]", addr.ip()),
+        Some(addr) => addr.ip().to_string(),
+        None => target.host().to_string(),
+    };
+    match (target.port(), tls) {
+        (80, false) | (443, true) => host,
+        (port, _) => format!("{host}:{port}"),
+    }
+}
+
 /// Run a session until the configured probe count is reached, the handler
 /// breaks, `exit_on_success` trips, or the cancellation future completes.
 pub async fn run_with_handler_until<F, C>(
@@ -232,17 +750,80 @@ where
 {
     options.validate()?;
 
-    let resolved = resolve_target(&options.target).await?;
+    let mut base = options.socket.clone();
+    base.close_mode = options.close_mode();
+    if let Some(tls) = base.tls.as_mut() {
+        tls.server_name.get_or_insert_with(|| {
+            base.http
+                .as_ref()
+                .and_then(HttpConfig::server_name)
+                .unwrap_or_else(|| tls_server_name(&options.target))
+        });
+        // Offer the protocol the request speaks; gRPC servers require h2.
+        if tls.alpn.is_empty() {
+            if base.http.is_some() {
+                tls.alpn.push("http/1.1".into());
+            } else if base.grpc.is_some() {
+                tls.alpn.push("h2".into());
+            }
+        }
+        tls.prepare()?;
+    }
+    if let Some(http) = base.http.as_mut() {
+        http.host
+            .get_or_insert_with(|| http_host(&options.target, base.tls.is_some()));
+    }
+    if let Some(grpc) = base.grpc.as_mut() {
+        grpc.authority
+            .get_or_insert_with(|| http_host(&options.target, base.tls.is_some()));
+    }
+
+    // Behind a proxy the probes connect to the proxy, not the target.
+    let resolved = resolve_target(
+        options
+            .proxy
+            .as_ref()
+            .map_or(&options.target, |p| &p.server),
+    )
+    .await?;
+    options.socket.check_family(resolved.addr)?;
     let mut stats = Stats::new(resolved.addr, resolved.resolve_time_ms, options.timestamps);
+    if !options.source_ports.is_empty() {
+        stats = stats.track_source_ports();
+    }
+    if !options.socket.options.is_empty() {
+        stats = stats.with_socket_options(options.socket.options);
+    }
+    stats = stats.with_close_mode(options.close_mode());
+    if let Some(days) = options.cert_warn_days {
+        stats = stats.with_cert_warn_days(days);
+    }
 
     if handler(PingEvent::Resolved(resolved.clone())).is_break() {
         return finish(&mut handler, &stats, options.timestamps);
     }
 
+    let mut reverse = options
+        .reverse_dns
+        .then(|| tokio::spawn(dns::reverse_lookup(resolved.addr.ip())));
+
     let mut ticker = time::interval(options.interval);
     ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
     ticker.tick().await;
 
+    if let Some(proxy) = &options.proxy {
+        let host = if proxy.kind.resolves_locally() {
+            resolve_target(&options.target).await?.addr.ip().to_string()
+        } else {
+            options.target.host().to_string()
+        };
+        base.tunnel = Some(Tunnel {
+            proxy: proxy.clone(),
+            host,
+            port: options.target.port(),
+        });
+    }
+
     tokio::pin!(cancel);
     let mut first = true;
 
@@ -256,13 +837,35 @@ where
             }
         }
 
-        let (ok, rtt) = tokio::select! {
-            result = probe_once(resolved.addr, options.timeout) => result,
+        let mut socket = base.clone();
+        let mut sweep_addr = None;
+        if !options.source_ports.is_empty() {
+            socket.source_port =
+                Some(options.source_ports[stats.attempts() % options.source_ports.len()]);
+            sweep_addr = socket.local_addr(resolved.addr);
+        }
+
+        let output = tokio::select! {
+            result = probe_once(resolved.addr, options.timeout, socket) => match result {
+                // During a sweep a port held elsewhere only fails its own probes.
+                Err(TcpingError::Io(err))
+                    if sweep_addr.is_some()
+                        && matches!(
+                            err.kind(),
+                            io::ErrorKind::AddrInUse | io::ErrorKind::AddrNotAvailable
+                        ) =>
+                {
+                    ProbeOutput::source_port_busy(sweep_addr)
+                }
+                result => result?,
+            },
             _ = &mut cancel => break,
         };
+        let ok = output.success;
 
+        collect_reverse(&mut reverse, &mut stats).await;
         let timestamp = options.timestamps.then(RecordTimestamp::now);
-        let probe = stats.feed(ok, rtt, options.jitter, timestamp);
+        let probe = stats.feed(&output, options.jitter, timestamp);
         if handler(PingEvent::Probe(probe)).is_break() {
             break;
         }
@@ -272,9 +875,22 @@ where
         }
     }
 
+    collect_reverse(&mut reverse, &mut stats).await;
+    if let Some(task) = reverse {
+        task.abort();
+    }
     finish(&mut handler, &stats, options.timestamps)
 }
 
+/// Move a finished PTR lookup into `stats` without waiting on one still in flight.
+async fn collect_reverse(reverse: &mut Option<JoinHandle<Option<String>>>, stats: &mut Stats) {
+    if let Some(task) = reverse.take_if(|task| task.is_finished())
+        && let Ok(Some(name)) = task.await
+    {
+        stats.set_ptr_name(name);
+    }
+}
+
 fn finish<F>(handler: &mut F, stats: &Stats, timestamps: bool) -> Result<Summary>
 where
     F: FnMut(PingEvent) -> ControlFlow<()>,
@@ -301,6 +917,116 @@ mod tests {
         assert!(ProbeCount::fixed(0).is_err());
     }
 
+    #[test]
+    fn fixed_source_port_conflicts_with_sweep() {
+        let target = Target::parse("127.0.0.1:80").unwrap();
+        let options = PingOptions::new(target)
+            .with_source_port(40_000)
+            .with_source_port_sweep([40_001, 40_002]);
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn pinned_source_ports_default_to_rst_close() {
+        let target = Target::parse("127.0.0.1:80").unwrap();
+        let options = PingOptions::new(target);
+        assert_eq!(options.close_mode(), CloseMode::Fin);
+        assert_eq!(
+            options.clone().with_source_port(40_000).close_mode(),
+            CloseMode::Rst
+        );
+        assert_eq!(
+            options
+                .with_source_port_sweep([40_001])
+                .with_close_mode(CloseMode::HalfClose)
+                .close_mode(),
+            CloseMode::HalfClose
+        );
+    }
+
+    #[test]
+    fn banner_pattern_must_compile() {
+        let target = Target::parse("127.0.0.1:22").unwrap();
+        assert!(PingOptions::new(target.clone()).expect_banner("(").is_err());
+
+        let options = PingOptions::new(target).expect_banner("^SSH-").unwrap();
+        assert!(options.reads_banner());
+        assert_eq!(options.banner_pattern(), Some("^SSH-"));
+    }
+
+    #[test]
+    fn http_host_omits_default_ports() {
+        let host = |address: &str, tls| http_host(&Target::parse(address).unwrap(), tls);
+        assert_eq!(host("example.com:80", false), "example.com");
+        assert_eq!(host("example.com:443", false), "example.com:443");
+        assert_eq!(host("example.com:443", true), "example.com");
+        assert_eq!(host("[::1]:8080", false), "[::1]:8080");
+    }
+
+    #[test]
+    fn https_requests_enable_tls() {
+        let target = Target::parse("127.0.0.1:443").unwrap();
+        let options = PingOptions::new(target.clone())
+            .http_request("https://example.com/healthz")
+            .unwrap();
+        assert!(options.uses_http() && options.uses_tls());
+        assert!(
+            !PingOptions::new(target.clone())
+                .http_request("/")
+                .unwrap()
+                .uses_tls()
+        );
+        assert!(
+            PingOptions::new(target)
+                .http_request("/")
+                .unwrap()
+                .expect_status("7xx")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn proxy_header_addresses_need_the_proxy_protocol() {
+        let options = || PingOptions::new(Target::parse("127.0.0.1:80").unwrap());
+        let addr: SocketAddr = "203.0.113.7:40000".parse().unwrap();
+        assert!(options().with_proxy_source(addr).is_err());
+        assert!(options().with_proxy_destination(addr).is_err());
+        assert!(
+            options()
+                .with_proxy_protocol(ProxyProtocol::V1)
+                .with_proxy_source(addr)
+                .and_then(|options| options.with_proxy_destination(addr))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn dns_rcodes_need_a_dns_query() {
+        let options = || PingOptions::new(Target::parse("127.0.0.1:53").unwrap());
+        assert!(options().expect_dns_rcode("NXDOMAIN").is_err());
+        assert!(
+            options()
+                .dns_query("example.com", "A")
+                .and_then(|options| options.expect_dns_rcode("NXDOMAIN"))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn http_settings_need_an_http_request() {
+        let options = || PingOptions::new(Target::parse("127.0.0.1:80").unwrap());
+        assert!(options().with_http_method("HEAD").is_err());
+        assert!(options().with_http_header("X-Probe", "1").is_err());
+        assert!(options().expect_status("2xx").is_err());
+        assert!(
+            options()
+                .http_request("/")
+                .and_then(|options| options.with_http_method("HEAD"))
+                .and_then(|options| options.expect_status("2xx"))
+                .is_ok()
+        );
+    }
+
     #[tokio::test]
     async fn collect_rejects_continuous_sessions() {
         let target = Target::parse("127.0.0.1:80").unwrap();
@@ -0,0 +1,172 @@
+//! DNS SRV discovery for service names such as `_ldap._tcp.example.com`.
+
+use crate::{
+    dns::{self, RecordData},
+    error::{Result, TcpingError},
+    target::Target,
+};
+use std::{
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// One host:port published in an SRV record set.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub host: String,
+}
+
+impl SrvRecord {
+    /// Probe target for this record.
+    pub fn target(&self) -> Result<Target> {
+        Target::new(self.host.as_str(), self.port)
+    }
+}
+
+/// Resolve an SRV name through the nameservers in `/etc/resolv.conf`,
+/// moving on to the next one when a server does not answer.
+pub async fn resolve_srv(name: &str) -> Result<Vec<SrvRecord>> {
+    let mut error = TcpingError::Dns("no system DNS server configured".into());
+    for server in dns::system_nameservers() {
+        let server = SocketAddr::new(server, dns::DNS_PORT);
+        match dns::query(server, name, dns::TYPE_SRV, dns::QUERY_TIMEOUT).await {
+            Ok(response) => return records(name, response),
+            Err(err) => error = err,
+        }
+    }
+    Err(error)
+}
+
+/// Resolve an SRV name against a specific DNS server.
+///
+/// Records are ordered by ascending priority and, within one priority, by the
+/// weighted random selection described in RFC 2782.
+pub async fn resolve_srv_with(name: &str, server: SocketAddr) -> Result<Vec<SrvRecord>> {
+    let response = dns::query(server, name, dns::TYPE_SRV, dns::QUERY_TIMEOUT).await?;
+    records(name, response)
+}
+
+/// Ordered records from an SRV response.
+fn records(name: &str, response: dns::Message) -> Result<Vec<SrvRecord>> {
+    response.check_rcode(name)?;
+
+    let records: Vec<SrvRecord> = response
+        .answers
+        .into_iter()
+        .filter_map(|record| match record.data {
+            RecordData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => Some(SrvRecord {
+                priority,
+                weight,
+                port,
+                host: target,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    match records.as_slice() {
+        [] => Err(TcpingError::Dns(format!("no SRV records for {name}"))),
+        [record] if record.host == "." => Err(TcpingError::Dns(format!(
+            "service {name} is explicitly unavailable"
+        ))),
+        _ => Ok(order_records(records, seed())),
+    }
+}
+
+fn order_records(mut records: Vec<SrvRecord>, mut state: u64) -> Vec<SrvRecord> {
+    records.retain(|record| record.host != ".");
+    records.sort_by_key(|record| record.priority);
+
+    let mut ordered = Vec::with_capacity(records.len());
+    for group in records.chunk_by(|a, b| a.priority == b.priority) {
+        // RFC 2782 places zero-weight records first so they keep a small
+        // chance of being selected early.
+        let mut pending = group.to_vec();
+        pending.sort_by_key(|record| record.weight != 0);
+
+        while !pending.is_empty() {
+            let total: u64 = pending.iter().map(|record| u64::from(record.weight)).sum();
+            let pick = next_random(&mut state) % (total + 1);
+            let mut running = 0;
+            let index = pending
+                .iter()
+                .position(|record| {
+                    running += u64::from(record.weight);
+                    running >= pick
+                })
+                .unwrap_or(0);
+            ordered.push(pending.remove(index));
+        }
+    }
+    ordered
+}
+
+fn seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    nanos | 1
+}
+
+/// xorshift64; plenty for spreading SRV selection.
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(priority: u16, weight: u16, host: &str) -> SrvRecord {
+        SrvRecord {
+            priority,
+            weight,
+            port: 389,
+            host: host.into(),
+        }
+    }
+
+    #[test]
+    fn lower_priority_always_comes_first() {
+        let records = vec![
+            record(20, 100, "backup"),
+            record(10, 1, "a"),
+            record(10, 50, "b"),
+        ];
+
+        for seed in 1..=32 {
+            let ordered = order_records(records.clone(), seed);
+            assert_eq!(ordered.len(), 3);
+            assert_eq!(ordered[2].host, "backup");
+        }
+    }
+
+    #[test]
+    fn heavy_weight_is_preferred_within_a_priority() {
+        let records = vec![record(10, 0, "light"), record(10, 65_535, "heavy")];
+
+        for seed in 1..=32 {
+            let ordered = order_records(records.clone(), seed);
+            assert_eq!(ordered[0].host, "heavy");
+            assert_eq!(ordered[1].host, "light");
+        }
+    }
+
+    #[test]
+    fn record_maps_to_probe_target() {
+        let target = record(10, 1, "ldap.example.com").target().unwrap();
+        assert_eq!(target.to_string(), "ldap.example.com:389");
+    }
+}
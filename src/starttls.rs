@@ -0,0 +1,262 @@
+//! Plaintext STARTTLS negotiation ahead of a TLS probe handshake.
+//!
+//! Each protocol runs its greeting and upgrade command on the raw socket and
+//! stops once the server is waiting for a ClientHello.
+
+// Without the `tls` feature there is no handshake to negotiate for.
+#![cfg_attr(not(feature = "tls"), allow(dead_code))]
+
+use crate::{banner::sanitise, wire::Wire};
+use socket2::Socket;
+use std::{fmt, time::Duration};
+
+/// Protocol spoken before upgrading the connection to TLS.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum StartTls {
+    /// `EHLO` then `STARTTLS` (RFC 3207).
+    Smtp,
+    /// `STARTTLS` command (RFC 9051).
+    Imap,
+    /// `STLS` command (RFC 2595).
+    Pop3,
+    /// StartTLS extended operation (RFC 4511).
+    Ldap,
+}
+
+impl StartTls {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Smtp => "smtp",
+            Self::Imap => "imap",
+            Self::Pop3 => "pop3",
+            Self::Ldap => "ldap",
+        }
+    }
+}
+
+impl fmt::Display for StartTls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// LDAPMessage { messageID 1, ExtendedRequest { requestName 1.3.6.1.4.1.1466.20037 } }.
+const LDAP_STARTTLS_REQUEST: &[u8] = b"\x30\x1d\x02\x01\x01\x77\x18\x80\x16\
+1.3.6.1.4.1.1466.20037";
+
+/// Longest reply line or LDAP message accepted before giving up.
+const MAX_REPLY: usize = 16 * 1024;
+
+/// Plaintext conversation on the raw socket.
+type Plaintext<'a> = Wire<'a, &'a Socket>;
+
+/// Run the plaintext exchange for `protocol` until the server is ready for TLS.
+pub(crate) fn negotiate(
+    socket: &Socket,
+    protocol: StartTls,
+    timeout: Duration,
+) -> Result<(), String> {
+    let mut io = socket;
+    let mut conn = Wire::new(&mut io, socket, timeout, MAX_REPLY);
+    match protocol {
+        StartTls::Smtp => smtp(&mut conn),
+        StartTls::Imap => imap(&mut conn),
+        StartTls::Pop3 => pop3(&mut conn),
+        StartTls::Ldap => ldap(&mut conn),
+    }
+}
+
+fn smtp(conn: &mut Plaintext<'_>) -> Result<(), String> {
+    smtp_reply(conn, "220")?;
+    conn.send(b"EHLO tcping\r\n")?;
+    let extensions = smtp_reply(conn, "250")?;
+    if !extensions
+        .iter()
+        .any(|ext| ext.eq_ignore_ascii_case("STARTTLS"))
+    {
+        return Err("server does not offer STARTTLS".into());
+    }
+    conn.send(b"STARTTLS\r\n")?;
+    smtp_reply(conn, "220").map(drop)
+}
+
+fn imap(conn: &mut Plaintext<'_>) -> Result<(), String> {
+    let greeting = conn.line()?;
+    if !greeting.starts_with("* OK") {
+        return Err(format!(
+            "unexpected greeting: {}",
+            sanitise(greeting.as_bytes())
+        ));
+    }
+    conn.send(b"a1 STARTTLS\r\n")?;
+    loop {
+        let line = conn.line()?;
+        if line.starts_with("a1 OK") {
+            return Ok(());
+        }
+        if line.starts_with("a1 ") {
+            return Err(format!(
+                "server refused STARTTLS: {}",
+                sanitise(line.as_bytes())
+            ));
+        }
+    }
+}
+
+fn pop3(conn: &mut Plaintext<'_>) -> Result<(), String> {
+    let greeting = conn.line()?;
+    if !greeting.starts_with("+OK") {
+        return Err(format!(
+            "unexpected greeting: {}",
+            sanitise(greeting.as_bytes())
+        ));
+    }
+    conn.send(b"STLS\r\n")?;
+    let reply = conn.line()?;
+    if !reply.starts_with("+OK") {
+        return Err(format!(
+            "server refused STLS: {}",
+            sanitise(reply.as_bytes())
+        ));
+    }
+    Ok(())
+}
+
+fn ldap(conn: &mut Plaintext<'_>) -> Result<(), String> {
+    conn.send(LDAP_STARTTLS_REQUEST)?;
+    let message = ber_sequence(conn)?;
+    match ldap_result_code(&message) {
+        Some(0) => Ok(()),
+        Some(code) => Err(format!("server refused StartTLS (LDAP result {code})")),
+        None => Err("unexpected LDAP response".into()),
+    }
+}
+
+/// Result code of an ExtendedResponse, given the LDAPMessage contents.
+fn ldap_result_code(message: &[u8]) -> Option<u8> {
+    let (tag, _, rest) = ber_element(message)?;
+    if tag != 0x02 {
+        return None;
+    }
+    let (tag, response, _) = ber_element(rest)?;
+    if tag != 0x78 {
+        return None;
+    }
+    match ber_element(response)? {
+        (0x0a, [code], _) => Some(*code),
+        _ => None,
+    }
+}
+
+/// Split one BER element off `input`: tag, contents, remainder.
+fn ber_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (len, rest) = ber_length(rest)?;
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// Definite BER length: short form or up to four long-form octets.
+fn ber_length(input: &[u8]) -> Option<(usize, &[u8])> {
+    let (&first, rest) = input.split_first()?;
+    if first & 0x80 == 0 {
+        return Some((usize::from(first), rest));
+    }
+
+    let octets = usize::from(first & 0x7f);
+    if !(1..=4).contains(&octets) || rest.len() < octets {
+        return None;
+    }
+    let len = rest[..octets]
+        .iter()
+        .fold(0, |len, &byte| (len << 8) | usize::from(byte));
+    Some((len, &rest[octets..]))
+}
+
+/// A complete, possibly multiline, SMTP reply with the expected code;
+/// returns the text of each line.
+fn smtp_reply(conn: &mut Plaintext<'_>, code: &str) -> Result<Vec<String>, String> {
+    let mut lines = Vec::new();
+    loop {
+        let line = conn.line()?;
+        if !line.starts_with(code) {
+            return Err(format!("unexpected reply: {}", sanitise(line.as_bytes())));
+        }
+        let more = line.as_bytes().get(3) == Some(&b'-');
+        lines.push(line.get(4..).unwrap_or_default().to_string());
+        if !more {
+            return Ok(lines);
+        }
+    }
+}
+
+/// Contents of one complete BER SEQUENCE.
+fn ber_sequence(conn: &mut Plaintext<'_>) -> Result<Vec<u8>, String> {
+    loop {
+        if conn.pending.first().is_some_and(|&tag| tag != 0x30) {
+            return Err("unexpected LDAP response".into());
+        }
+        if let Some((_, contents, rest)) = ber_element(&conn.pending) {
+            let contents = contents.to_vec();
+            let used = conn.pending.len() - rest.len();
+            conn.pending.drain(..used);
+            return Ok(contents);
+        }
+        if conn.pending.len() > MAX_REPLY {
+            return Err("LDAP response too long".into());
+        }
+        conn.fill()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn ldap_request_is_well_formed() {
+        let (tag, message, rest) = ber_element(LDAP_STARTTLS_REQUEST).unwrap();
+        assert_eq!((tag, rest), (0x30, &[][..]));
+        let (_, _, op) = ber_element(message).unwrap();
+        let (tag, request, _) = ber_element(op).unwrap();
+        assert_eq!(tag, 0x77);
+        assert_eq!(
+            ber_element(request).unwrap(),
+            (0x80, &b"1.3.6.1.4.1.1466.20037"[..], &[][..])
+        );
+    }
+
+    #[test]
+    fn ldap_result_code_is_read_from_the_extended_response() {
+        // messageID 1, ExtendedResponse { resultCode, matchedDN "", diagnosticMessage "" }
+        let success = b"\x02\x01\x01\x78\x07\x0a\x01\x00\x04\x00\x04\x00";
+        assert_eq!(ldap_result_code(success), Some(0));
+        let unavailable = b"\x02\x01\x01\x78\x84\x00\x00\x00\x07\x0a\x01\x34\x04\x00\x04\x00";
+        assert_eq!(ldap_result_code(unavailable), Some(52));
+        assert_eq!(ldap_result_code(b"\x02\x01\x01\x61\x00"), None);
+    }
+
+    #[test]
+    fn smtp_requires_an_advertised_starttls() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"220 mail ESMTP\r\n").unwrap();
+            let mut buf = [0; 64];
+            let _ = stream.read(&mut buf).unwrap();
+            stream
+                .write_all(b"250-mail greets tcping\r\n250 8BITMIME\r\n")
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+        });
+
+        let socket = Socket::from(std::net::TcpStream::connect(addr).unwrap());
+        let result = negotiate(&socket, StartTls::Smtp, Duration::from_secs(1));
+        server.join().unwrap();
+        assert_eq!(result, Err("server does not offer STARTTLS".into()));
+    }
+}
@@ -4,9 +4,20 @@
 //! Both [PingResult] and [Summary] are serde-serialisable so the
 //! formatting layer can dump them directly.
 
-use std::net::SocketAddr;
-
-use crate::timestamp::RecordTimestamp;
+use std::{collections::BTreeMap, net::SocketAddr};
+
+use crate::{
+    dns::DnsInfo,
+    grpc::GrpcInfo,
+    http::HttpInfo,
+    probe::{CloseMode, ProbeOutcome, ProbeOutput, SocketOptions},
+    protocol::ProtocolInfo,
+    proxy::ProxyInfo,
+    script::StepResult,
+    tcp_info::TcpInfo,
+    timestamp::RecordTimestamp,
+    tls::{CertInfo, TlsInfo},
+};
 
 pub const OUTPUT_SCHEMA_V1: &str = "tcping.v1";
 pub const OUTPUT_SCHEMA_V2: &str = "tcping.v2";
@@ -172,6 +183,7 @@ impl P2Quantile {
 /// Result of a single probe.
 ///
 /// This structure may be serialised as JSON / CSV by the formatter layer.
+#[non_exhaustive]
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PingResult {
@@ -179,12 +191,49 @@ pub struct PingResult {
     pub record: &'static str,
     pub timestamp: Option<RecordTimestamp>,
     pub success: bool,
+    /// Finer classification behind `success`.
+    pub outcome: ProbeOutcome,
     pub duration_ms: f64,
+    /// With a hold window: time from connect until the server closed or
+    /// reset the connection.
+    pub time_to_close_ms: Option<f64>,
+    /// Banner mode: time from connect to the first byte the server sent.
+    pub time_to_first_byte_ms: Option<f64>,
+    /// Banner mode: the server's first line, with control bytes escaped.
+    pub banner: Option<String>,
+    /// Send/expect steps run after connect, in order, up to the first failure.
+    pub steps: Vec<StepResult>,
+    /// TLS mode: handshake timing (separate from `duration_ms`) and the
+    /// negotiated parameters.
+    pub tls: Option<TlsInfo>,
+    /// HTTP mode: status and timings of the request sent after connect.
+    pub http: Option<HttpInfo>,
+    /// gRPC health mode: serving status and call time.
+    pub grpc: Option<GrpcInfo>,
+    /// Protocol mode: application round trip, apart from `duration_ms`.
+    pub protocol: Option<ProtocolInfo>,
+    /// DNS mode: response code and query time, apart from `duration_ms`.
+    pub dns: Option<DnsInfo>,
+    /// Proxy mode: time for the proxy to open the tunnel, apart from
+    /// `duration_ms` (the time to reach the proxy).
+    pub proxy: Option<ProxyInfo>,
     pub jitter_ms: Option<f64>,
     pub addr: SocketAddr,
+    pub local_addr: Option<SocketAddr>,
+    pub ptr_name: Option<String>,
+    /// Socket options the probe was sent with, if any were requested.
+    pub socket_options: Option<SocketOptions>,
+    /// Kernel handshake details of a successful connect (Linux only).
+    pub tcp_info: Option<TcpInfo>,
+    /// Connected only after a retransmitted SYN, so `duration_ms`
+    /// includes at least one retransmission timeout.
+    pub retransmitted: bool,
+    /// How the connection was closed; `None` for failed probes.
+    pub close_mode: Option<CloseMode>,
 }
 
 /// Roll-up of an entire probing session.
+#[non_exhaustive]
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Summary {
@@ -192,6 +241,7 @@ pub struct Summary {
     pub record: &'static str,
     pub timestamp: Option<RecordTimestamp>,
     pub addr: SocketAddr,
+    pub ptr_name: Option<String>,
     pub total_attempts: usize,
     pub successful_pings: usize,
     pub packet_loss: f64,
@@ -200,12 +250,91 @@ pub struct Summary {
     pub max_duration_ms: f64,
     pub resolve_time_ms: f64,
     pub jitter_p95_ms: Option<f64>,
+    /// Per-source-port breakdown; empty unless a source port sweep is active.
+    pub source_ports: Vec<SourcePortStats>,
+    /// Kernel RTT statistics over successful probes (Linux only).
+    pub tcp_info: Option<TcpInfoStats>,
+    /// How successful probe connections were closed.
+    pub close_mode: CloseMode,
+    /// Leaf certificate from the most recent TLS handshake.
+    pub certificate: Option<CertInfo>,
+    /// Expiry threshold in days; see [Summary::cert_expiring].
+    pub cert_warn_days: Option<u32>,
+}
+
+impl PingResult {
+    /// A probe record for `addr` without any mode-specific details, for
+    /// callers such as formatter tests that need one outside a session.
+    pub fn new(addr: SocketAddr, outcome: ProbeOutcome, duration_ms: f64) -> Self {
+        Self {
+            schema: OUTPUT_SCHEMA_V1,
+            record: "probe",
+            timestamp: None,
+            success: outcome.is_success(),
+            outcome,
+            duration_ms,
+            time_to_close_ms: None,
+            time_to_first_byte_ms: None,
+            banner: None,
+            steps: Vec::new(),
+            tls: None,
+            http: None,
+            grpc: None,
+            protocol: None,
+            dns: None,
+            proxy: None,
+            jitter_ms: None,
+            addr,
+            local_addr: None,
+            ptr_name: None,
+            socket_options: None,
+            tcp_info: None,
+            retransmitted: false,
+            close_mode: None,
+        }
+    }
 }
 
 impl Summary {
+    /// An empty summary for `addr` (no probes sent), for callers that need
+    /// one outside a session.
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            schema: OUTPUT_SCHEMA_V1,
+            record: "summary",
+            timestamp: None,
+            addr,
+            ptr_name: None,
+            total_attempts: 0,
+            successful_pings: 0,
+            packet_loss: 0.0,
+            min_duration_ms: 0.0,
+            avg_duration_ms: 0.0,
+            max_duration_ms: 0.0,
+            resolve_time_ms: 0.0,
+            jitter_p95_ms: None,
+            source_ports: Vec::new(),
+            tcp_info: None,
+            close_mode: CloseMode::default(),
+            certificate: None,
+            cert_warn_days: None,
+        }
+    }
+
+    /// Whether the certificate expires within `cert_warn_days`.
+    pub fn cert_expiring(&self) -> bool {
+        match (&self.certificate, self.cert_warn_days) {
+            (Some(cert), Some(days)) => cert.days_remaining < i64::from(days),
+            _ => false,
+        }
+    }
+
     /// Map this summary to a conventional Unix process exit code.
     pub fn exit_code(&self) -> i32 {
-        if self.successful_pings == self.total_attempts && self.total_attempts > 0 {
+        if self.successful_pings == self.total_attempts
+            && self.total_attempts > 0
+            && !self.cert_expiring()
+        {
             0
         } else {
             1
@@ -213,14 +342,176 @@ impl Summary {
     }
 }
 
-/// Mutable accumulator used during a session.
-pub(crate) struct Stats {
-    addr: SocketAddr,
+/// Statistics for the probes sent from one local port during a sweep.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct SourcePortStats {
+    pub source_port: u16,
+    pub total_attempts: usize,
+    pub successful_pings: usize,
+    pub packet_loss: f64,
+    pub min_duration_ms: f64,
+    pub avg_duration_ms: f64,
+    pub max_duration_ms: f64,
+}
+
+impl SourcePortStats {
+    /// Empty statistics for `source_port` (no probes sent), for callers that
+    /// need them outside a session.
+    pub fn new(source_port: u16) -> Self {
+        Self {
+            source_port,
+            total_attempts: 0,
+            successful_pings: 0,
+            packet_loss: 0.0,
+            min_duration_ms: 0.0,
+            avg_duration_ms: 0.0,
+            max_duration_ms: 0.0,
+        }
+    }
+}
+
+/// Aggregate of the kernel `TCP_INFO` snapshots taken during a session.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct TcpInfoStats {
+    pub samples: usize,
+    pub min_rtt_ms: f64,
+    pub avg_rtt_ms: f64,
+    pub max_rtt_ms: f64,
+    pub avg_rttvar_ms: f64,
+    pub total_retransmits: u64,
+}
+
+impl TcpInfoStats {
+    /// An aggregate of `samples` snapshots with every figure zero, for
+    /// callers that need one outside a session.
+    pub fn new(samples: usize) -> Self {
+        Self {
+            samples,
+            min_rtt_ms: 0.0,
+            avg_rtt_ms: 0.0,
+            max_rtt_ms: 0.0,
+            avg_rttvar_ms: 0.0,
+            total_retransmits: 0,
+        }
+    }
+}
+
+/// Running totals behind [TcpInfoStats].
+#[derive(Clone, Debug)]
+struct KernelTally {
+    samples: usize,
+    total_rtt: f64,
+    min_rtt: f64,
+    max_rtt: f64,
+    total_rttvar: f64,
+    retransmits: u64,
+}
+
+impl KernelTally {
+    fn new() -> Self {
+        Self {
+            samples: 0,
+            total_rtt: 0.0,
+            min_rtt: f64::MAX,
+            max_rtt: 0.0,
+            total_rttvar: 0.0,
+            retransmits: 0,
+        }
+    }
+
+    fn record(&mut self, info: &TcpInfo) {
+        self.samples += 1;
+        self.total_rtt += info.rtt_ms;
+        self.min_rtt = self.min_rtt.min(info.rtt_ms);
+        self.max_rtt = self.max_rtt.max(info.rtt_ms);
+        self.total_rttvar += info.rttvar_ms;
+        self.retransmits += u64::from(info.retransmits);
+    }
+
+    fn summary(&self) -> TcpInfoStats {
+        let samples = self.samples as f64;
+        TcpInfoStats {
+            samples: self.samples,
+            min_rtt_ms: self.min_rtt,
+            avg_rtt_ms: self.total_rtt / samples,
+            max_rtt_ms: self.max_rtt,
+            avg_rttvar_ms: self.total_rttvar / samples,
+            total_retransmits: self.retransmits,
+        }
+    }
+}
+
+/// Attempt and RTT counters shared by the session totals and per-key breakdowns.
+#[derive(Clone, Debug)]
+struct Tally {
     sent: usize,
     ok: usize,
     total_rtt: f64,
     min_rtt: f64,
     max_rtt: f64,
+}
+
+impl Tally {
+    fn new() -> Self {
+        Self {
+            sent: 0,
+            ok: 0,
+            total_rtt: 0.0,
+            min_rtt: f64::MAX,
+            max_rtt: 0.0,
+        }
+    }
+
+    fn record(&mut self, success: bool, rtt: f64) {
+        self.sent += 1;
+        if success {
+            self.ok += 1;
+            self.total_rtt += rtt;
+            self.min_rtt = self.min_rtt.min(rtt);
+            self.max_rtt = self.max_rtt.max(rtt);
+        }
+    }
+
+    fn packet_loss(&self) -> f64 {
+        if self.sent == 0 {
+            0.0
+        } else {
+            100.0 * (1.0 - self.ok as f64 / self.sent as f64)
+        }
+    }
+
+    fn min(&self) -> f64 {
+        if self.ok > 0 { self.min_rtt } else { 0.0 }
+    }
+
+    fn avg(&self) -> f64 {
+        if self.ok > 0 {
+            self.total_rtt / self.ok as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn max(&self) -> f64 {
+        if self.ok > 0 { self.max_rtt } else { 0.0 }
+    }
+}
+
+/// Mutable accumulator used during a session.
+pub(crate) struct Stats {
+    addr: SocketAddr,
+    ptr_name: Option<String>,
+    totals: Tally,
+    by_source_port: Option<BTreeMap<u16, Tally>>,
+    socket_options: Option<SocketOptions>,
+    kernel: Option<KernelTally>,
+    close_mode: CloseMode,
+    certificate: Option<CertInfo>,
+    cert_warn_days: Option<u32>,
     last_rtt: Option<f64>,
     resolve_ms: f64,
     jitter_p95: Option<P2Quantile>,
@@ -232,11 +523,14 @@ impl Stats {
     pub(crate) fn new(addr: SocketAddr, resolve_ms: f64, include_timestamps: bool) -> Self {
         Self {
             addr,
-            sent: 0,
-            ok: 0,
-            total_rtt: 0.0,
-            min_rtt: f64::MAX,
-            max_rtt: 0.0,
+            ptr_name: None,
+            totals: Tally::new(),
+            by_source_port: None,
+            socket_options: None,
+            kernel: None,
+            close_mode: CloseMode::default(),
+            certificate: None,
+            cert_warn_days: None,
             last_rtt: None,
             resolve_ms,
             jitter_p95: None,
@@ -244,15 +538,44 @@ impl Stats {
         }
     }
 
+    /// Additionally break statistics down by the local port of each probe.
+    pub(crate) fn track_source_ports(mut self) -> Self {
+        self.by_source_port = Some(BTreeMap::new());
+        self
+    }
+
+    /// Report how successful probes are closed.
+    pub(crate) fn with_close_mode(mut self, close_mode: CloseMode) -> Self {
+        self.close_mode = close_mode;
+        self
+    }
+
+    /// Flag certificates expiring within `days` in the summary.
+    pub(crate) fn with_cert_warn_days(mut self, days: u32) -> Self {
+        self.cert_warn_days = Some(days);
+        self
+    }
+
+    /// Echo the socket options in use on every probe record.
+    pub(crate) fn with_socket_options(mut self, options: SocketOptions) -> Self {
+        self.socket_options = Some(options);
+        self
+    }
+
+    /// Record the PTR name of the probed address once it is known.
+    pub(crate) fn set_ptr_name(&mut self, name: String) {
+        self.ptr_name = Some(name);
+    }
+
     /// Feed one probe result and obtain a [PingResult] to hand to the formatter.
     pub(crate) fn feed(
         &mut self,
-        success: bool,
-        rtt: f64,
+        output: &ProbeOutput,
         want_jitter: bool,
         timestamp: Option<RecordTimestamp>,
     ) -> PingResult {
-        self.sent += 1;
+        let success = output.success;
+        let rtt = output.rtt_ms;
 
         let jitter = if want_jitter && success {
             self.last_rtt.map(|prev| (rtt - prev).abs())
@@ -266,55 +589,97 @@ impl Stats {
                 .observe(j);
         }
 
+        self.totals.record(success, rtt);
         if success {
-            self.ok += 1;
-            self.total_rtt += rtt;
-            self.min_rtt = self.min_rtt.min(rtt);
-            self.max_rtt = self.max_rtt.max(rtt);
             self.last_rtt = Some(rtt);
         }
 
+        if let Some(info) = output.tcp_info.as_ref() {
+            self.kernel
+                .get_or_insert_with(KernelTally::new)
+                .record(info);
+        }
+
+        if let Some(cert) = output.tls.as_ref().and_then(|tls| tls.certificate.as_ref()) {
+            self.certificate = Some(cert.clone());
+        }
+
+        if let (Some(ports), Some(local)) = (self.by_source_port.as_mut(), output.local_addr) {
+            ports
+                .entry(local.port())
+                .or_insert_with(Tally::new)
+                .record(success, rtt);
+        }
+
         PingResult {
             schema: self.schema,
             record: "probe",
             timestamp,
             success,
+            outcome: output.outcome,
             duration_ms: rtt,
+            time_to_close_ms: output.time_to_close_ms,
+            time_to_first_byte_ms: output.ttfb_ms,
+            banner: output.banner.clone(),
+            steps: output.steps.clone(),
+            tls: output.tls.clone(),
+            http: output.http.clone(),
+            grpc: output.grpc.clone(),
+            protocol: output.protocol.clone(),
+            dns: output.dns.clone(),
+            proxy: output.proxy.clone(),
             jitter_ms: jitter,
             addr: self.addr,
+            local_addr: output.local_addr,
+            ptr_name: self.ptr_name.clone(),
+            socket_options: self.socket_options,
+            tcp_info: output.tcp_info,
+            retransmitted: output.retransmitted,
+            close_mode: output.close_mode,
         }
     }
 
     /// Number of probes recorded so far.
     pub(crate) fn attempts(&self) -> usize {
-        self.sent
+        self.totals.sent
     }
 
     /// Produce the final [Summary].
     pub(crate) fn summary(&self, timestamp: Option<RecordTimestamp>) -> Summary {
-        let packet_loss = if self.sent == 0 {
-            0.0
-        } else {
-            100.0 * (1.0 - self.ok as f64 / self.sent as f64)
-        };
+        let source_ports = self
+            .by_source_port
+            .iter()
+            .flatten()
+            .map(|(&source_port, tally)| SourcePortStats {
+                source_port,
+                total_attempts: tally.sent,
+                successful_pings: tally.ok,
+                packet_loss: tally.packet_loss(),
+                min_duration_ms: tally.min(),
+                avg_duration_ms: tally.avg(),
+                max_duration_ms: tally.max(),
+            })
+            .collect();
 
         Summary {
             schema: self.schema,
             record: "summary",
             timestamp,
             addr: self.addr,
-            total_attempts: self.sent,
-            successful_pings: self.ok,
-            packet_loss,
-            min_duration_ms: if self.ok > 0 { self.min_rtt } else { 0.0 },
-            avg_duration_ms: if self.ok > 0 {
-                self.total_rtt / self.ok as f64
-            } else {
-                0.0
-            },
-            max_duration_ms: if self.ok > 0 { self.max_rtt } else { 0.0 },
+            ptr_name: self.ptr_name.clone(),
+            total_attempts: self.totals.sent,
+            successful_pings: self.totals.ok,
+            packet_loss: self.totals.packet_loss(),
+            min_duration_ms: self.totals.min(),
+            avg_duration_ms: self.totals.avg(),
+            max_duration_ms: self.totals.max(),
             resolve_time_ms: self.resolve_ms,
             jitter_p95_ms: self.jitter_p95.as_ref().and_then(|q| q.estimate()),
+            source_ports,
+            tcp_info: self.kernel.as_ref().map(KernelTally::summary),
+            close_mode: self.close_mode,
+            certificate: self.certificate.clone(),
+            cert_warn_days: self.cert_warn_days,
         }
     }
 }
@@ -328,6 +693,39 @@ mod tests {
         SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 80)
     }
 
+    fn output(success: bool, rtt_ms: f64) -> ProbeOutput {
+        ProbeOutput {
+            success,
+            outcome: if success {
+                ProbeOutcome::Open
+            } else {
+                ProbeOutcome::Closed
+            },
+            rtt_ms,
+            time_to_close_ms: None,
+            ttfb_ms: None,
+            banner: None,
+            steps: Vec::new(),
+            tls: None,
+            http: None,
+            grpc: None,
+            protocol: None,
+            dns: None,
+            proxy: None,
+            local_addr: None,
+            tcp_info: None,
+            retransmitted: false,
+            close_mode: success.then_some(CloseMode::Fin),
+        }
+    }
+
+    fn output_from_port(success: bool, rtt_ms: f64, port: u16) -> ProbeOutput {
+        ProbeOutput {
+            local_addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)),
+            ..output(success, rtt_ms)
+        }
+    }
+
     #[test]
     fn summary_handles_zero_probes() {
         let stats = Stats::new(loopback_addr(), 0.0, false);
@@ -339,20 +737,20 @@ mod tests {
     #[test]
     fn jitter_is_difference_between_successive_successes() {
         let mut stats = Stats::new(loopback_addr(), 0.0, false);
-        let first = stats.feed(true, 10.0, true, None);
+        let first = stats.feed(&output(true, 10.0), true, None);
         assert_eq!(first.jitter_ms, None);
 
-        let second = stats.feed(true, 15.0, true, None);
+        let second = stats.feed(&output(true, 15.0), true, None);
         assert_eq!(second.jitter_ms, Some(5.0));
     }
 
     #[test]
     fn jitter_p95_is_reported_when_enabled() {
         let mut stats = Stats::new(loopback_addr(), 0.0, false);
-        stats.feed(true, 10.0, true, None);
-        stats.feed(true, 20.0, true, None); // jitter 10
-        stats.feed(true, 25.0, true, None); // jitter 5
-        stats.feed(true, 40.0, true, None); // jitter 15
+        stats.feed(&output(true, 10.0), true, None);
+        stats.feed(&output(true, 20.0), true, None); // jitter 10
+        stats.feed(&output(true, 25.0), true, None); // jitter 5
+        stats.feed(&output(true, 40.0), true, None); // jitter 15
 
         let summary = stats.summary(None);
         assert_eq!(summary.jitter_p95_ms, Some(14.5));
@@ -361,8 +759,8 @@ mod tests {
     #[test]
     fn jitter_p95_is_none_when_disabled() {
         let mut stats = Stats::new(loopback_addr(), 0.0, false);
-        stats.feed(true, 10.0, false, None);
-        stats.feed(true, 20.0, false, None);
+        stats.feed(&output(true, 10.0), false, None);
+        stats.feed(&output(true, 20.0), false, None);
 
         let summary = stats.summary(None);
         assert_eq!(summary.jitter_p95_ms, None);
@@ -371,16 +769,145 @@ mod tests {
     #[test]
     fn jitter_is_only_computed_for_successes() {
         let mut stats = Stats::new(loopback_addr(), 0.0, false);
-        let first = stats.feed(true, 10.0, true, None);
+        let first = stats.feed(&output(true, 10.0), true, None);
         assert_eq!(first.jitter_ms, None);
 
-        let failed = stats.feed(false, 10_000.0, true, None);
+        let failed = stats.feed(&output(false, 10_000.0), true, None);
         assert_eq!(failed.jitter_ms, None);
 
-        let second = stats.feed(true, 20.0, true, None);
+        let second = stats.feed(&output(true, 20.0), true, None);
         assert_eq!(second.jitter_ms, Some(10.0));
     }
 
+    #[test]
+    fn ptr_name_is_attached_once_known() {
+        let mut stats = Stats::new(loopback_addr(), 0.0, false);
+        assert_eq!(stats.feed(&output(true, 10.0), false, None).ptr_name, None);
+
+        stats.set_ptr_name("localhost".into());
+        let probe = stats.feed(&output(true, 10.0), false, None);
+        assert_eq!(probe.ptr_name.as_deref(), Some("localhost"));
+        assert_eq!(stats.summary(None).ptr_name.as_deref(), Some("localhost"));
+    }
+
+    #[test]
+    fn source_ports_are_only_reported_when_tracked() {
+        let mut stats = Stats::new(loopback_addr(), 0.0, false);
+        stats.feed(&output_from_port(true, 10.0, 40_000), false, None);
+        assert!(stats.summary(None).source_ports.is_empty());
+    }
+
+    #[test]
+    fn loss_is_broken_down_by_source_port() {
+        let mut stats = Stats::new(loopback_addr(), 0.0, false).track_source_ports();
+        for _ in 0..2 {
+            stats.feed(&output_from_port(true, 10.0, 40_000), false, None);
+            stats.feed(&output_from_port(false, 2_000.0, 40_001), false, None);
+            stats.feed(&output_from_port(true, 20.0, 40_002), false, None);
+        }
+
+        let summary = stats.summary(None);
+        assert_eq!(summary.total_attempts, 6);
+        assert_eq!(summary.successful_pings, 4);
+        assert_eq!(summary.source_ports.len(), 3);
+
+        let bad = &summary.source_ports[1];
+        assert_eq!(bad.source_port, 40_001);
+        assert_eq!(bad.total_attempts, 2);
+        assert_eq!(bad.successful_pings, 0);
+        assert_eq!(bad.packet_loss, 100.0);
+        assert_eq!(bad.avg_duration_ms, 0.0);
+
+        let good = &summary.source_ports[2];
+        assert_eq!(good.packet_loss, 0.0);
+        assert_eq!(good.avg_duration_ms, 20.0);
+    }
+
+    #[test]
+    fn kernel_rtt_is_summarised_from_tcp_info() {
+        let mut stats = Stats::new(loopback_addr(), 0.0, false);
+        stats.feed(&output(false, 2_000.0), false, None);
+        assert_eq!(stats.summary(None).tcp_info, None);
+
+        for (rtt_ms, retransmits) in [(1.0, 0), (3.0, 1)] {
+            let info = TcpInfo {
+                rtt_ms,
+                rttvar_ms: 0.5,
+                snd_mss: 1_448,
+                rcv_mss: 536,
+                retransmits,
+                snd_wscale: Some(7),
+                rcv_wscale: Some(7),
+            };
+            let probe = stats.feed(
+                &ProbeOutput {
+                    tcp_info: Some(info),
+                    ..output(true, rtt_ms + 0.2)
+                },
+                false,
+                None,
+            );
+            assert_eq!(probe.tcp_info, Some(info));
+        }
+
+        let kernel = stats.summary(None).tcp_info.unwrap();
+        assert_eq!(kernel.samples, 2);
+        assert_eq!(kernel.min_rtt_ms, 1.0);
+        assert_eq!(kernel.avg_rtt_ms, 2.0);
+        assert_eq!(kernel.max_rtt_ms, 3.0);
+        assert_eq!(kernel.avg_rttvar_ms, 0.5);
+        assert_eq!(kernel.total_retransmits, 1);
+    }
+
+    #[test]
+    fn expiring_certificate_fails_the_exit_code() {
+        let tls = |days_remaining| TlsInfo {
+            handshake_ms: 1.0,
+            starttls_ms: None,
+            version: Some("TLSv1.3".into()),
+            cipher: None,
+            alpn: None,
+            error: None,
+            certificate: Some(CertInfo {
+                subject: "CN=example.com".into(),
+                issuer: "CN=Example CA".into(),
+                sans: vec!["example.com".into()],
+                not_after: "2030-01-15T00:00:00Z".into(),
+                days_remaining,
+                chain_len: 2,
+                name_matches: true,
+            }),
+        };
+
+        let mut stats = Stats::new(loopback_addr(), 0.0, false);
+        stats.feed(
+            &ProbeOutput {
+                tls: Some(tls(10)),
+                ..output(true, 10.0)
+            },
+            false,
+            None,
+        );
+        let summary = stats.summary(None);
+        assert_eq!(summary.certificate.as_ref().unwrap().days_remaining, 10);
+        assert!(!summary.cert_expiring());
+        assert_eq!(summary.exit_code(), 0);
+
+        let mut stats = stats.with_cert_warn_days(14);
+        assert!(stats.summary(None).cert_expiring());
+        assert_eq!(stats.summary(None).exit_code(), 1);
+
+        stats.feed(
+            &ProbeOutput {
+                tls: Some(tls(30)),
+                ..output(true, 10.0)
+            },
+            false,
+            None,
+        );
+        assert_eq!(stats.summary(None).exit_code(), 0);
+    }
+
     #[test]
     fn schema_upgrades_only_when_timestamps_are_enabled() {
         let without_timestamps = Stats::new(loopback_addr(), 0.0, false);
@@ -1,12 +1,13 @@
 //! Target parsing and DNS resolution.
 
 use crate::{
+    dns,
     error::{Result, TcpingError},
     timestamp::RecordTimestamp,
 };
 use std::{
     fmt,
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6},
     str::FromStr,
     time::Instant,
 };
@@ -15,7 +16,9 @@ use std::{
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Target {
     host: String,
+    unicode_host: String,
     port: u16,
+    scope_id: Option<u32>,
 }
 
 impl Target {
@@ -33,22 +36,54 @@ impl Target {
             ));
         }
 
+        let scope_id = match host.split_once('%') {
+            Some((ip, zone)) => Some(parse_zone(ip, zone)?),
+            None => None,
+        };
+
+        let (ascii, unicode) = if scope_id.is_some() || host.parse::<IpAddr>().is_ok() {
+            (host.to_string(), host.to_string())
+        } else {
+            idna_forms(host)?
+        };
+
         Ok(Self {
-            host: host.to_string(),
+            host: ascii,
+            unicode_host: unicode,
             port,
+            scope_id,
         })
     }
 
-    /// Parse `<host:port>`, including bracketed IPv6 targets such as `[::1]:443`.
+    /// Parse `<host:port>`, including bracketed IPv6 targets such as `[::1]:443`
+    /// and link-local targets with a zone such as `[fe80::1%eth0]:22`.
     pub fn parse(address: &str) -> Result<Self> {
         address.parse()
     }
 
-    /// Hostname or IP literal.
+    /// Hostname or IP literal, including any `%zone` suffix. Internationalised
+    /// names are returned in their ASCII (punycode) form, as sent to the resolver.
     pub fn host(&self) -> &str {
         &self.host
     }
 
+    /// Hostname in its Unicode form for display, e.g. `例子.测试` rather than
+    /// `xn--fsqu00a.xn--0zwm56d`. Identical to [`Target::host`] for plain ASCII
+    /// names and IP literals.
+    pub fn unicode_host(&self) -> &str {
+        &self.unicode_host
+    }
+
+    /// IPv6 zone identifier as written by the user, if any.
+    pub fn zone(&self) -> Option<&str> {
+        self.host.split_once('%').map(|(_, zone)| zone)
+    }
+
+    /// Numeric IPv6 scope ID derived from the zone identifier, if any.
+    pub fn scope_id(&self) -> Option<u32> {
+        self.scope_id
+    }
+
     /// TCP port.
     pub fn port(&self) -> u16 {
         self.port
@@ -56,16 +91,109 @@ impl Target {
 
     /// Whether the host is already an IP literal and does not require DNS.
     pub fn is_ip_literal(&self) -> bool {
-        self.host.parse::<IpAddr>().is_ok()
+        self.ip_literal().is_some()
     }
 
     /// Socket address if this target is an IP literal.
     pub fn socket_addr_if_literal(&self) -> Option<SocketAddr> {
-        self.host
-            .parse::<IpAddr>()
-            .ok()
-            .map(|ip| SocketAddr::new(ip, self.port))
+        match self.ip_literal()? {
+            IpAddr::V6(ip) => Some(SocketAddr::V6(SocketAddrV6::new(
+                ip,
+                self.port,
+                0,
+                self.scope_id.unwrap_or(0),
+            ))),
+            ip => Some(SocketAddr::new(ip, self.port)),
+        }
+    }
+
+    fn ip_literal(&self) -> Option<IpAddr> {
+        let ip = self
+            .host
+            .split_once('%')
+            .map_or(self.host.as_str(), |(ip, _)| ip);
+        ip.parse().ok()
+    }
+}
+
+/// ASCII and Unicode forms of a hostname. Plain ASCII names are kept as
+/// given; anything else goes through UTS #46 / IDNA processing.
+fn idna_forms(host: &str) -> Result<(String, String)> {
+    let ascii = if host.is_ascii() {
+        host.to_string()
+    } else {
+        idna::domain_to_ascii(host).map_err(|_| {
+            TcpingError::InvalidTarget(format!(
+                "'{host}' is not a valid internationalised domain name"
+            ))
+        })?
+    };
+
+    let has_punycode = ascii
+        .split('.')
+        .any(|label| label.len() > 4 && label[..4].eq_ignore_ascii_case("xn--"));
+    let unicode = if has_punycode {
+        match idna::domain_to_unicode(&ascii) {
+            (unicode, Ok(())) => unicode,
+            (_, Err(_)) => ascii.clone(),
+        }
+    } else {
+        ascii.clone()
+    };
+
+    Ok((ascii, unicode))
+}
+
+/// Map a `%zone` suffix to a scope ID. Zones may be a numeric index or an
+/// interface name, and are only meaningful on IPv6 literals.
+fn parse_zone(ip: &str, zone: &str) -> Result<u32> {
+    if ip.parse::<Ipv6Addr>().is_err() {
+        return Err(TcpingError::InvalidTarget(
+            "zone identifiers are only valid on IPv6 literals".into(),
+        ));
+    }
+
+    if zone.is_empty() {
+        return Err(TcpingError::InvalidTarget("IPv6 zone is empty".into()));
     }
+
+    if let Ok(index) = zone.parse::<u32>() {
+        return Ok(index);
+    }
+
+    if !cfg!(any(unix, windows)) {
+        return Err(TcpingError::InvalidTarget(format!(
+            "interface zone '{zone}' is not supported on this platform; use a numeric zone ID"
+        )));
+    }
+    interface_index(zone)
+        .ok_or_else(|| TcpingError::InvalidTarget(format!("unknown interface '{zone}'")))
+}
+
+#[cfg(unix)]
+fn interface_index(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: `name` is a valid NUL-terminated string for the duration of the call.
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    (index != 0).then_some(index)
+}
+
+#[cfg(windows)]
+fn interface_index(name: &str) -> Option<u32> {
+    #[link(name = "iphlpapi")]
+    unsafe extern "system" {
+        fn if_nametoindex(name: *const std::ffi::c_char) -> u32;
+    }
+
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: `name` is a valid NUL-terminated string for the duration of the call.
+    let index = unsafe { if_nametoindex(name.as_ptr()) };
+    (index != 0).then_some(index)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn interface_index(_name: &str) -> Option<u32> {
+    None
 }
 
 impl FromStr for Target {
@@ -101,7 +229,7 @@ impl fmt::Display for Target {
         if self.host.contains(':') && self.is_ip_literal() {
             write!(f, "[{}]:{}", self.host, self.port)
         } else {
-            write!(f, "{}:{}", self.host, self.port)
+            write!(f, "{}:{}", self.unicode_host, self.port)
         }
     }
 }
@@ -117,25 +245,6 @@ pub struct ResolvedTarget {
     pub resolved_at: RecordTimestamp,
 }
 
-/// Best-effort reader for the first DNS server listed in `/etc/resolv.conf`.
-/// Returns `None` on Windows or failure.
-fn first_dns_server() -> Option<IpAddr> {
-    #[cfg(unix)]
-    {
-        use std::fs::File;
-        use std::io::{BufRead, BufReader};
-
-        let file = File::open("/etc/resolv.conf").ok()?;
-        for line in BufReader::new(file).lines().map_while(|line| line.ok()) {
-            let line = line.trim_start();
-            if let Some(rest) = line.strip_prefix("nameserver") {
-                return rest.trim().parse().ok();
-            }
-        }
-    }
-    None
-}
-
 /// Resolve a target to the first socket address returned by the system resolver.
 pub async fn resolve_target(target: &Target) -> Result<ResolvedTarget> {
     if let Some(addr) = target.socket_addr_if_literal() {
@@ -157,7 +266,7 @@ pub async fn resolve_target(target: &Target) -> Result<ResolvedTarget> {
         target: target.clone(),
         addr,
         resolve_time_ms,
-        dns_server: first_dns_server(),
+        dns_server: dns::system_nameserver(),
         resolved_at: RecordTimestamp::now(),
     })
 }
@@ -199,6 +308,55 @@ mod tests {
         assert!(target.socket_addr_if_literal().is_none());
     }
 
+    #[test]
+    fn parses_ipv6_literal_with_numeric_zone() {
+        let target = Target::parse("[fe80::1%3]:22").unwrap();
+        assert!(target.is_ip_literal());
+        assert_eq!(target.zone(), Some("3"));
+        assert_eq!(target.scope_id(), Some(3));
+        assert_eq!(
+            target.socket_addr_if_literal(),
+            Some("[fe80::1%3]:22".parse().unwrap())
+        );
+        assert_eq!(target.to_string(), "[fe80::1%3]:22");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_ipv6_literal_with_interface_zone() {
+        let target = Target::parse("[fe80::1%lo]:22").unwrap();
+        assert_eq!(target.zone(), Some("lo"));
+        assert_eq!(target.scope_id(), Some(1));
+        assert_eq!(target.to_string(), "[fe80::1%lo]:22");
+        assert_eq!(Target::parse(&target.to_string()).unwrap(), target);
+    }
+
+    #[test]
+    fn rejects_invalid_zones() {
+        assert!(Target::parse("[fe80::1%]:22").is_err());
+        assert!(Target::parse("[fe80::1%no-such-if0]:22").is_err());
+        assert!(Target::parse("127.0.0.1%1:80").is_err());
+    }
+
+    #[test]
+    fn converts_internationalised_names_to_punycode() {
+        let target = Target::parse("例子.测试:443").unwrap();
+        assert!(!target.is_ip_literal());
+        assert_eq!(target.host(), "xn--fsqu00a.xn--0zwm56d");
+        assert_eq!(target.unicode_host(), "例子.测试");
+        assert_eq!(target.to_string(), "例子.测试:443");
+
+        let punycode = Target::parse("xn--fsqu00a.xn--0zwm56d:443").unwrap();
+        assert_eq!(punycode, target);
+    }
+
+    #[test]
+    fn ascii_names_are_kept_verbatim() {
+        let target = Target::parse("Example.COM:80").unwrap();
+        assert_eq!(target.host(), "Example.COM");
+        assert_eq!(target.unicode_host(), "Example.COM");
+    }
+
     #[test]
     fn rejects_missing_port() {
         assert!(Target::parse("example.com").is_err());
@@ -0,0 +1,118 @@
+//! Kernel `TCP_INFO` snapshot of a connected probe socket (Linux only).
+//!
+//! The kernel's smoothed RTT excludes thread scheduling noise that the
+//! wall-clock connect time includes, which helps separate network latency
+//! from host latency.
+
+use socket2::Socket;
+
+/// Handshake details reported by the kernel right after connect.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TcpInfo {
+    /// Smoothed round-trip time.
+    pub rtt_ms: f64,
+    /// Round-trip time variance.
+    pub rttvar_ms: f64,
+    /// Negotiated send MSS.
+    pub snd_mss: u32,
+    /// Receive MSS estimate.
+    pub rcv_mss: u32,
+    /// Segments retransmitted on this connection, including SYNs.
+    pub retransmits: u32,
+    /// Send window scale, if window scaling was negotiated.
+    pub snd_wscale: Option<u8>,
+    /// Receive window scale, if window scaling was negotiated.
+    pub rcv_wscale: Option<u8>,
+}
+
+/// Read `TCP_INFO` from a connected socket.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn read(socket: &Socket) -> Option<TcpInfo> {
+    use std::os::fd::AsRawFd;
+
+    /// Stable prefix of the kernel's `struct tcp_info` (uapi/linux/tcp.h).
+    /// The kernel copies `min(len, sizeof)` bytes, so a prefix is enough and
+    /// avoids differences between libc flavours.
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct RawTcpInfo {
+        state: u8,
+        ca_state: u8,
+        retransmits: u8,
+        probes: u8,
+        backoff: u8,
+        options: u8,
+        wscale: u8,
+        app_limited: u8,
+        rto: u32,
+        ato: u32,
+        snd_mss: u32,
+        rcv_mss: u32,
+        unacked: u32,
+        sacked: u32,
+        lost: u32,
+        retrans: u32,
+        fackets: u32,
+        last_data_sent: u32,
+        last_ack_sent: u32,
+        last_data_recv: u32,
+        last_ack_recv: u32,
+        pmtu: u32,
+        rcv_ssthresh: u32,
+        rtt: u32,
+        rttvar: u32,
+        snd_ssthresh: u32,
+        snd_cwnd: u32,
+        advmss: u32,
+        reordering: u32,
+        rcv_rtt: u32,
+        rcv_space: u32,
+        total_retrans: u32,
+    }
+
+    const TCPI_OPT_WSCALE: u8 = 4;
+
+    let mut raw = RawTcpInfo::default();
+    let mut len = std::mem::size_of::<RawTcpInfo>() as libc::socklen_t;
+    // SAFETY: `raw` is a plain-old-data buffer of `len` bytes that outlives
+    // the call, and the kernel never writes more than `len` bytes.
+    let rc = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            (&mut raw as *mut RawTcpInfo).cast(),
+            &mut len,
+        )
+    };
+    if rc != 0 || (len as usize) < std::mem::size_of::<RawTcpInfo>() {
+        return None;
+    }
+
+    // `tcpi_snd_wscale : 4, tcpi_rcv_wscale : 4` share one byte; bitfield
+    // allocation starts at the least significant bit on little-endian targets.
+    let (snd_wscale, rcv_wscale) = if cfg!(target_endian = "little") {
+        (raw.wscale & 0x0f, raw.wscale >> 4)
+    } else {
+        (raw.wscale >> 4, raw.wscale & 0x0f)
+    };
+    let scaled = raw.options & TCPI_OPT_WSCALE != 0;
+
+    Some(TcpInfo {
+        rtt_ms: f64::from(raw.rtt) / 1_000.0,
+        rttvar_ms: f64::from(raw.rttvar) / 1_000.0,
+        snd_mss: raw.snd_mss,
+        rcv_mss: raw.rcv_mss,
+        retransmits: raw.total_retrans,
+        snd_wscale: scaled.then_some(snd_wscale),
+        rcv_wscale: scaled.then_some(rcv_wscale),
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn read(_socket: &Socket) -> Option<TcpInfo> {
+    None
+}
@@ -0,0 +1,880 @@
+//! TLS handshake timing on a connected probe socket (`tls` feature).
+//!
+//! The handshake runs with rustls on the same blocking socket right after
+//! connect, so its time is reported apart from the TCP connect RTT. The
+//! server's certificate chain is recorded by the verifier, so the leaf is
+//! described even when verification fails (e.g. it has already expired).
+
+use crate::{probe::ProbeOutcome, starttls::StartTls};
+use socket2::Socket;
+use std::{path::PathBuf, time::Duration};
+
+/// What a TLS probe negotiated, or why the handshake failed.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TlsInfo {
+    /// Time from connect (or STARTTLS) to handshake completion or failure,
+    /// not counting the wait for a verdict on a TLS 1.3 client certificate.
+    pub handshake_ms: f64,
+    /// Time of the plaintext STARTTLS exchange before the handshake.
+    pub starttls_ms: Option<f64>,
+    /// Negotiated protocol version, e.g. `TLSv1.3`.
+    pub version: Option<String>,
+    /// Negotiated cipher suite, e.g. `TLS13_AES_128_GCM_SHA256`.
+    pub cipher: Option<String>,
+    /// Protocol selected through ALPN.
+    pub alpn: Option<String>,
+    /// Handshake failure, e.g. an untrusted certificate or a timeout.
+    pub error: Option<String>,
+    /// Leaf certificate the server presented, if it got that far.
+    pub certificate: Option<CertInfo>,
+}
+
+/// Details of the leaf certificate a server presented.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CertInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// DNS names and IP addresses from the subjectAltName extension.
+    pub sans: Vec<String>,
+    /// Expiry in RFC 3339 UTC, e.g. `2026-12-01T23:59:59Z`.
+    pub not_after: String,
+    /// Whole days left until expiry when the probe ran; negative once expired.
+    pub days_remaining: i64,
+    /// Number of certificates the server sent, leaf included.
+    pub chain_len: usize,
+    /// Whether a SAN covers the name the probe connected to.
+    pub name_matches: bool,
+}
+
+/// TLS settings for a probe.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct TlsSettings {
+    /// SNI and certificate name; the target host when unset.
+    pub(crate) server_name: Option<String>,
+    /// ALPN protocols to offer, in preference order.
+    pub(crate) alpn: Vec<String>,
+    /// Accept any server certificate.
+    pub(crate) insecure: bool,
+    /// PEM client certificate chain and private key for mTLS.
+    pub(crate) client_cert: Option<PathBuf>,
+    pub(crate) client_key: Option<PathBuf>,
+    /// PEM bundle of trusted CAs replacing the built-in roots.
+    pub(crate) ca_file: Option<PathBuf>,
+    /// Plaintext protocol to upgrade from before the handshake.
+    pub(crate) starttls: Option<StartTls>,
+    /// Client configuration built once per session by `prepare`.
+    pub(crate) client: Option<ClientTls>,
+}
+
+impl TlsSettings {
+    /// Reject settings that can never produce a handshake.
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        use crate::TcpingError;
+
+        if !cfg!(feature = "tls") {
+            return Err(TcpingError::InvalidOptions(
+                "TLS probes require tcping to be built with the `tls` feature".into(),
+            ));
+        }
+
+        if self
+            .alpn
+            .iter()
+            .any(|protocol| protocol.is_empty() || protocol.len() > 255)
+        {
+            return Err(TcpingError::InvalidOptions(
+                "ALPN protocol names must be 1 to 255 bytes".into(),
+            ));
+        }
+
+        #[cfg(feature = "tls")]
+        {
+            if let Some(name) = &self.server_name {
+                rustls::pki_types::ServerName::try_from(name.as_str()).map_err(|_| {
+                    TcpingError::InvalidOptions(format!("invalid TLS server name: {name}"))
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the client configuration once the server name and ALPN are
+    /// settled, so each probe times only its own handshake. The certificate,
+    /// key and CA files are read here and nowhere else, so a bad path fails
+    /// the session instead of every probe.
+    pub(crate) fn prepare(&mut self) -> crate::Result<()> {
+        #[cfg(feature = "tls")]
+        {
+            self.client = Some(ClientTls::new(self).map_err(crate::TcpingError::InvalidOptions)?);
+        }
+        Ok(())
+    }
+}
+
+/// Verifier, client certificate and rustls configuration shared by the
+/// probes of a session.
+#[cfg(feature = "tls")]
+#[derive(Clone, Debug)]
+pub(crate) struct ClientTls {
+    config: std::sync::Arc<rustls::ClientConfig>,
+    recorder: std::sync::Arc<Recorder>,
+    client_auth: std::sync::Arc<ClientAuth>,
+}
+
+#[cfg(feature = "tls")]
+impl ClientTls {
+    fn new(settings: &TlsSettings) -> std::result::Result<Self, String> {
+        let recorder = Recorder::new(settings)?;
+        let client_auth = ClientAuth::new(settings)?;
+        let config = client_config(settings, recorder.clone(), client_auth.clone())?;
+        Ok(Self {
+            config,
+            recorder,
+            client_auth,
+        })
+    }
+}
+
+#[cfg(feature = "tls")]
+impl PartialEq for ClientTls {
+    fn eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.config, &other.config)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Eq for ClientTls {}
+
+/// Without the `tls` feature no configuration is ever built.
+#[cfg(not(feature = "tls"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ClientTls {}
+
+/// Established TLS session on a probe socket.
+#[cfg(feature = "tls")]
+pub(crate) type Connection = rustls::ClientConnection;
+
+/// Without the `tls` feature no session is ever established.
+#[cfg(not(feature = "tls"))]
+pub(crate) enum Connection {}
+
+/// Run a TLS handshake on a connected socket; the session is returned when
+/// it succeeds so a request can follow on it.
+#[cfg(feature = "tls")]
+pub(crate) fn handshake(
+    socket: &Socket,
+    settings: &TlsSettings,
+    timeout: Duration,
+) -> (ProbeOutcome, TlsInfo, Option<Connection>) {
+    use std::time::Instant;
+
+    let mut starttls_ms = None;
+    if let Some(protocol) = settings.starttls {
+        let start = Instant::now();
+        let negotiated = crate::starttls::negotiate(socket, protocol, timeout);
+        starttls_ms = Some(start.elapsed().as_secs_f64() * 1_000.0);
+        if let Err(error) = negotiated {
+            return (
+                ProbeOutcome::StartTlsFailed,
+                TlsInfo {
+                    handshake_ms: 0.0,
+                    starttls_ms,
+                    version: None,
+                    cipher: None,
+                    alpn: None,
+                    error: Some(error),
+                    certificate: None,
+                },
+                None,
+            );
+        }
+    }
+
+    let name = settings.server_name.as_deref().unwrap_or_default();
+    let client = settings
+        .client
+        .clone()
+        .ok_or_else(|| String::from("TLS client configuration was not prepared"));
+    let start = Instant::now();
+    let result = client
+        .as_ref()
+        .map_err(|error| (ProbeOutcome::TlsFailed, error.clone()))
+        .and_then(|client| run_handshake(socket, name, client, start, timeout));
+    // The wait for a verdict on a client certificate is not handshake time.
+    let handshake_ms = start.elapsed().as_secs_f64() * 1_000.0;
+    let result = result.and_then(|mut conn| {
+        if let Ok(client) = &client {
+            confirm_client_auth(&mut conn, socket, client, start, timeout)?;
+        }
+        Ok(conn)
+    });
+    let certificate = client
+        .ok()
+        .and_then(|client| client.recorder.certificate(name));
+
+    match result {
+        Ok(conn) => (
+            ProbeOutcome::Open,
+            TlsInfo {
+                handshake_ms,
+                starttls_ms,
+                version: conn.protocol_version().map(version_name),
+                cipher: conn
+                    .negotiated_cipher_suite()
+                    .and_then(|suite| suite.suite().as_str())
+                    .map(str::to_string),
+                alpn: conn
+                    .alpn_protocol()
+                    .map(|protocol| String::from_utf8_lossy(protocol).into_owned()),
+                error: None,
+                certificate,
+            },
+            Some(conn),
+        ),
+        Err((outcome, error)) => (
+            outcome,
+            TlsInfo {
+                handshake_ms,
+                starttls_ms,
+                version: None,
+                cipher: None,
+                alpn: None,
+                error: Some(error),
+                certificate,
+            },
+            None,
+        ),
+    }
+}
+
+/// Read/write on a probe connection: through the TLS session when there is
+/// one, else on the socket directly.
+pub(crate) struct Stream<'a> {
+    conn: Option<&'a mut Connection>,
+    socket: &'a Socket,
+}
+
+impl Stream<'_> {
+    /// Whether the stream runs over TLS.
+    pub(crate) fn is_tls(&self) -> bool {
+        self.conn.is_some()
+    }
+}
+
+impl std::io::Read for Stream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut socket = self.socket;
+        match self.conn.as_deref_mut() {
+            #[cfg(feature = "tls")]
+            Some(conn) => rustls::Stream::new(conn, &mut socket).read(buf),
+            #[cfg(not(feature = "tls"))]
+            Some(conn) => match *conn {},
+            None => socket.read(buf),
+        }
+    }
+}
+
+impl std::io::Write for Stream<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut socket = self.socket;
+        match self.conn.as_deref_mut() {
+            #[cfg(feature = "tls")]
+            Some(conn) => rustls::Stream::new(conn, &mut socket).write(buf),
+            #[cfg(not(feature = "tls"))]
+            Some(conn) => match *conn {},
+            None => socket.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut socket = self.socket;
+        match self.conn.as_deref_mut() {
+            #[cfg(feature = "tls")]
+            Some(conn) => rustls::Stream::new(conn, &mut socket).flush(),
+            #[cfg(not(feature = "tls"))]
+            Some(conn) => match *conn {},
+            None => socket.flush(),
+        }
+    }
+}
+
+/// The stream for a probe connection, over `conn` when TLS is established.
+pub(crate) fn stream<'a>(conn: Option<&'a mut Connection>, socket: &'a Socket) -> Stream<'a> {
+    Stream { conn, socket }
+}
+
+/// Drive the handshake to completion.
+#[cfg(feature = "tls")]
+fn run_handshake(
+    socket: &Socket,
+    name: &str,
+    client: &ClientTls,
+    start: std::time::Instant,
+    timeout: Duration,
+) -> std::result::Result<rustls::ClientConnection, (ProbeOutcome, String)> {
+    let failed = |error: String| (ProbeOutcome::TlsFailed, error);
+    client.recorder.reset();
+    client.client_auth.reset();
+    let server_name = rustls::pki_types::ServerName::try_from(name.to_owned())
+        .map_err(|_| failed(format!("invalid TLS server name: {name}")))?;
+    let mut conn = rustls::ClientConnection::new(client.config.clone(), server_name)
+        .map_err(|err| (classify(&err), err.to_string()))?;
+
+    let mut io = socket;
+    while conn.is_handshaking() {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Err(failed("handshake timed out".into()));
+        }
+        socket
+            .set_read_timeout(Some(remaining))
+            .and_then(|()| socket.set_write_timeout(Some(remaining)))
+            .and_then(|()| conn.complete_io(&mut io))
+            .map_err(|err| match tls_error(&err) {
+                Some(tls) => (classify(tls), tls.to_string()),
+                None => failed(describe(&err)),
+            })?;
+    }
+    Ok(conn)
+}
+
+/// Wait for the server's verdict on a TLS 1.3 client certificate, if one was
+/// requested; the wait scales with the handshake time within `timeout`.
+#[cfg(feature = "tls")]
+fn confirm_client_auth(
+    conn: &mut rustls::ClientConnection,
+    socket: &Socket,
+    client: &ClientTls,
+    start: std::time::Instant,
+    timeout: Duration,
+) -> std::result::Result<(), (ProbeOutcome, String)> {
+    if !client.client_auth.requested()
+        || conn.protocol_version() != Some(rustls::ProtocolVersion::TLSv1_3)
+    {
+        return Ok(());
+    }
+    let window = start
+        .elapsed()
+        .max(MIN_VERDICT_WAIT)
+        .min(timeout.saturating_sub(start.elapsed()));
+    await_verdict(conn, socket, window)
+}
+
+/// The rustls error behind an I/O error from `complete_io`, if any.
+#[cfg(feature = "tls")]
+fn tls_error(err: &std::io::Error) -> Option<&rustls::Error> {
+    err.get_ref()?.downcast_ref()
+}
+
+#[cfg(not(feature = "tls"))]
+pub(crate) fn handshake(
+    _socket: &Socket,
+    _settings: &TlsSettings,
+    _timeout: Duration,
+) -> (ProbeOutcome, TlsInfo, Option<Connection>) {
+    (
+        ProbeOutcome::TlsFailed,
+        TlsInfo {
+            handshake_ms: 0.0,
+            starttls_ms: None,
+            version: None,
+            cipher: None,
+            alpn: None,
+            error: Some("built without the `tls` feature".into()),
+            certificate: None,
+        },
+        None,
+    )
+}
+
+/// Shortest wait for the server's verdict on a TLS 1.3 client certificate.
+#[cfg(feature = "tls")]
+const MIN_VERDICT_WAIT: Duration = Duration::from_millis(10);
+
+/// With TLS 1.3 the server checks a client certificate only after the
+/// client's Finished, so a rejection arrives as an alert after the client
+/// already considers the handshake done. Wait up to `window` for it; silence
+/// or any post-handshake record (e.g. a session ticket) counts as accepted.
+#[cfg(feature = "tls")]
+fn await_verdict(
+    conn: &mut rustls::ClientConnection,
+    socket: &Socket,
+    window: Duration,
+) -> std::result::Result<(), (ProbeOutcome, String)> {
+    use std::io::ErrorKind;
+
+    if window.is_zero() {
+        return Ok(());
+    }
+    let mut io = socket;
+    let read = socket
+        .set_read_timeout(Some(window))
+        .and_then(|()| conn.read_tls(&mut io));
+    match read {
+        Ok(0) => Err((
+            ProbeOutcome::TlsRejected,
+            "connection closed after client authentication".into(),
+        )),
+        Ok(_) => conn
+            .process_new_packets()
+            .map(drop)
+            .map_err(|err| (classify(&err), err.to_string())),
+        Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::ConnectionReset => Err((
+            ProbeOutcome::TlsRejected,
+            "connection reset after client authentication".into(),
+        )),
+        Err(err) => Err((ProbeOutcome::TlsFailed, err.to_string())),
+    }
+}
+
+/// Outcome for a TLS error: rejected by the server, or our own verification
+/// of the server's certificate failing.
+#[cfg(feature = "tls")]
+fn classify(err: &rustls::Error) -> ProbeOutcome {
+    use rustls::{CertificateError, Error};
+
+    match err {
+        Error::AlertReceived(_) => ProbeOutcome::TlsRejected,
+        Error::InvalidCertificate(CertificateError::UnknownIssuer) => ProbeOutcome::TlsUnknownCa,
+        Error::InvalidCertificate(_) => ProbeOutcome::TlsVerifyFailed,
+        _ => ProbeOutcome::TlsFailed,
+    }
+}
+
+#[cfg(feature = "tls")]
+fn client_config(
+    settings: &TlsSettings,
+    verifier: std::sync::Arc<Recorder>,
+    client_auth: std::sync::Arc<ClientAuth>,
+) -> std::result::Result<std::sync::Arc<rustls::ClientConfig>, String> {
+    use std::sync::Arc;
+
+    let mut config = rustls::ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| err.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_client_cert_resolver(client_auth);
+    config.alpn_protocols = settings
+        .alpn
+        .iter()
+        .map(|protocol| protocol.as_bytes().to_vec())
+        .collect();
+    Ok(Arc::new(config))
+}
+
+/// Trust anchors: the `ca_file` bundle when set, else the Mozilla roots.
+#[cfg(feature = "tls")]
+fn root_store(settings: &TlsSettings) -> std::result::Result<rustls::RootCertStore, String> {
+    let Some(path) = &settings.ca_file else {
+        return Ok(rustls::RootCertStore::from_iter(
+            webpki_roots::TLS_SERVER_ROOTS.iter().cloned(),
+        ));
+    };
+
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots
+            .add(cert)
+            .map_err(|err| format!("invalid CA certificate in {}: {err}", path.display()))?;
+    }
+    Ok(roots)
+}
+
+#[cfg(feature = "tls")]
+fn load_certs(
+    path: &std::path::Path,
+) -> std::result::Result<Vec<rustls::pki_types::CertificateDer<'static>>, String> {
+    use rustls::pki_types::{CertificateDer, pem::PemObject};
+
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(Iterator::collect::<std::result::Result<Vec<_>, _>>)
+        .map_err(|err| format!("cannot read certificates from {}: {err}", path.display()))?;
+    if certs.is_empty() {
+        return Err(format!("no certificates found in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+/// Client certificate for mTLS, remembering whether the server asked for one.
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+struct ClientAuth {
+    key: Option<std::sync::Arc<rustls::sign::CertifiedKey>>,
+    requested: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(feature = "tls")]
+impl ClientAuth {
+    fn new(settings: &TlsSettings) -> std::result::Result<std::sync::Arc<Self>, String> {
+        use rustls::pki_types::{PrivateKeyDer, pem::PemObject};
+
+        let key = match (&settings.client_cert, &settings.client_key) {
+            (Some(cert), Some(key)) => {
+                let chain = load_certs(cert)?;
+                let key = PrivateKeyDer::from_pem_file(key).map_err(|err| {
+                    format!("cannot read private key from {}: {err}", key.display())
+                })?;
+                let certified = rustls::sign::CertifiedKey::from_der(chain, key, &provider())
+                    .map_err(|err| format!("unusable client certificate: {err}"))?;
+                Some(std::sync::Arc::new(certified))
+            }
+            (None, None) => None,
+            _ => return Err("a client certificate needs both a certificate and a key".into()),
+        };
+        Ok(std::sync::Arc::new(Self {
+            key,
+            requested: std::sync::atomic::AtomicBool::new(false),
+        }))
+    }
+
+    fn requested(&self) -> bool {
+        self.requested.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Forget a previous handshake's certificate request.
+    fn reset(&self) {
+        self.requested
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "tls")]
+impl rustls::client::ResolvesClientCert for ClientAuth {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        _sigschemes: &[rustls::SignatureScheme],
+    ) -> Option<std::sync::Arc<rustls::sign::CertifiedKey>> {
+        self.requested
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        self.key.clone()
+    }
+
+    fn has_certs(&self) -> bool {
+        self.key.is_some()
+    }
+}
+
+#[cfg(feature = "tls")]
+fn provider() -> std::sync::Arc<rustls::crypto::CryptoProvider> {
+    std::sync::Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// Verifier wrapper that keeps the chain the server presented.
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+struct Recorder {
+    inner: std::sync::Arc<dyn rustls::client::danger::ServerCertVerifier>,
+    chain: std::sync::Mutex<Vec<rustls::pki_types::CertificateDer<'static>>>,
+}
+
+#[cfg(feature = "tls")]
+impl Recorder {
+    fn new(settings: &TlsSettings) -> std::result::Result<std::sync::Arc<Self>, String> {
+        let inner: std::sync::Arc<dyn rustls::client::danger::ServerCertVerifier> =
+            if settings.insecure {
+                std::sync::Arc::new(AcceptAnyCertificate(provider()))
+            } else {
+                rustls::client::WebPkiServerVerifier::builder_with_provider(
+                    std::sync::Arc::new(root_store(settings)?),
+                    provider(),
+                )
+                .build()
+                .map_err(|err| err.to_string())?
+            };
+        Ok(std::sync::Arc::new(Self {
+            inner,
+            chain: std::sync::Mutex::default(),
+        }))
+    }
+
+    /// Forget the chain recorded by a previous handshake.
+    fn reset(&self) {
+        if let Ok(mut chain) = self.chain.lock() {
+            chain.clear();
+        }
+    }
+
+    /// Describe the recorded leaf certificate against `server_name`.
+    fn certificate(&self, server_name: &str) -> Option<CertInfo> {
+        let chain = self.chain.lock().ok()?;
+        let (_, leaf) = x509_parser::parse_x509_certificate(chain.first()?).ok()?;
+        describe_certificate(
+            &leaf,
+            chain.len(),
+            server_name,
+            time::OffsetDateTime::now_utc(),
+        )
+    }
+}
+
+#[cfg(feature = "tls")]
+impl rustls::client::danger::ServerCertVerifier for Recorder {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::pki_types::CertificateDer<'_>,
+        intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        server_name: &rustls::pki_types::ServerName<'_>,
+        ocsp_response: &[u8],
+        now: rustls::pki_types::UnixTime,
+    ) -> std::result::Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        if let Ok(mut chain) = self.chain.lock() {
+            *chain = std::iter::once(end_entity)
+                .chain(intermediates)
+                .map(|cert| cert.clone().into_owned())
+                .collect();
+        }
+        self.inner
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(feature = "tls")]
+fn describe_certificate(
+    leaf: &x509_parser::certificate::X509Certificate<'_>,
+    chain_len: usize,
+    server_name: &str,
+    now: time::OffsetDateTime,
+) -> Option<CertInfo> {
+    use time::macros::format_description;
+    use x509_parser::extensions::GeneralName;
+
+    let sans: Vec<String> = leaf
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|ext| {
+            ext.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(dns) => Some((*dns).to_string()),
+                    GeneralName::IPAddress([a, b, c, d]) => {
+                        Some(std::net::Ipv4Addr::new(*a, *b, *c, *d).to_string())
+                    }
+                    GeneralName::IPAddress(bytes) => <[u8; 16]>::try_from(*bytes)
+                        .ok()
+                        .map(|octets| std::net::Ipv6Addr::from(octets).to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let not_after = leaf.validity().not_after.to_datetime();
+    Some(CertInfo {
+        subject: leaf.subject().to_string(),
+        issuer: leaf.issuer().to_string(),
+        name_matches: sans.iter().any(|san| name_matches(san, server_name)),
+        sans,
+        not_after: not_after
+            .format(format_description!(
+                "[year]-[month]-[day]T[hour]:[minute]:[second]Z"
+            ))
+            .ok()?,
+        days_remaining: (not_after - now).whole_days(),
+        chain_len,
+    })
+}
+
+/// RFC 6125 matching: case-insensitive, a leading `*` covers exactly one label.
+#[cfg(feature = "tls")]
+fn name_matches(san: &str, name: &str) -> bool {
+    let name = name.trim_end_matches('.');
+    if let Ok(ip) = name.parse::<std::net::IpAddr>() {
+        return san.parse() == Ok(ip);
+    }
+    match san.strip_prefix("*.") {
+        Some(suffix) => name
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest.eq_ignore_ascii_case(suffix)),
+        None => san.eq_ignore_ascii_case(name),
+    }
+}
+
+#[cfg(feature = "tls")]
+fn version_name(version: rustls::ProtocolVersion) -> String {
+    match version {
+        rustls::ProtocolVersion::TLSv1_2 => "TLSv1.2".into(),
+        rustls::ProtocolVersion::TLSv1_3 => "TLSv1.3".into(),
+        other => format!("{other:?}"),
+    }
+}
+
+#[cfg(feature = "tls")]
+fn describe(err: &std::io::Error) -> String {
+    use std::io::ErrorKind;
+
+    match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => "handshake timed out".into(),
+        ErrorKind::UnexpectedEof => "connection closed during handshake".into(),
+        ErrorKind::ConnectionReset => "connection reset during handshake".into(),
+        _ => err.to_string(),
+    }
+}
+
+/// Certificate verifier for `--insecure`: signatures are still checked so
+/// the handshake completes normally, but any certificate chain is accepted.
+#[cfg(feature = "tls")]
+#[derive(Debug)]
+struct AcceptAnyCertificate(std::sync::Arc<rustls::crypto::CryptoProvider>);
+
+#[cfg(feature = "tls")]
+impl rustls::client::danger::ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> std::result::Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> std::result::Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alpn_names_must_fit_the_wire_format() {
+        let settings = TlsSettings {
+            alpn: vec![String::new()],
+            ..TlsSettings::default()
+        };
+        assert!(settings.validate().is_err());
+        assert_eq!(
+            TlsSettings::default().validate().is_ok(),
+            cfg!(feature = "tls")
+        );
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn errors_are_classified() {
+        use rustls::{AlertDescription, CertificateError, Error};
+
+        assert_eq!(
+            classify(&Error::AlertReceived(AlertDescription::CertificateRequired)),
+            ProbeOutcome::TlsRejected
+        );
+        assert_eq!(
+            classify(&Error::InvalidCertificate(CertificateError::UnknownIssuer)),
+            ProbeOutcome::TlsUnknownCa
+        );
+        assert_eq!(
+            classify(&Error::InvalidCertificate(CertificateError::Expired)),
+            ProbeOutcome::TlsVerifyFailed
+        );
+        assert_eq!(classify(&Error::DecryptError), ProbeOutcome::TlsFailed);
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn wildcards_cover_one_label() {
+        assert!(name_matches("*.example.com", "www.Example.com"));
+        assert!(!name_matches("*.example.com", "example.com"));
+        assert!(!name_matches("*.example.com", "a.b.example.com"));
+        assert!(name_matches("example.com", "EXAMPLE.com."));
+        assert!(name_matches("::1", "0:0::1"));
+        assert!(!name_matches("127.0.0.1", "localhost"));
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn leaf_certificate_is_described() {
+        let mut params =
+            rcgen::CertificateParams::new(vec!["*.example.com".into(), "127.0.0.1".into()])
+                .unwrap();
+        params.not_after = rcgen::date_time_ymd(2030, 1, 15);
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "example.com");
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+        let (_, leaf) = x509_parser::parse_x509_certificate(cert.der()).unwrap();
+
+        let now = time::macros::datetime!(2030-01-01 00:00 UTC);
+        let info = describe_certificate(&leaf, 2, "www.example.com", now).unwrap();
+        assert_eq!(info.subject, "CN=example.com");
+        assert_eq!(info.issuer, "CN=example.com");
+        assert_eq!(info.sans, ["*.example.com", "127.0.0.1"]);
+        assert_eq!(info.not_after, "2030-01-15T00:00:00Z");
+        assert_eq!(info.days_remaining, 14);
+        assert_eq!(info.chain_len, 2);
+        assert!(info.name_matches);
+
+        let info = describe_certificate(&leaf, 1, "example.org", now).unwrap();
+        assert!(!info.name_matches);
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn server_name_must_be_valid() {
+        let settings = TlsSettings {
+            server_name: Some("bad name!".into()),
+            ..TlsSettings::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
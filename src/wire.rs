@@ -0,0 +1,141 @@
+//! Blocking request/response exchange on a probe connection, plain or TLS.
+//!
+//! STARTTLS, HTTP, gRPC, protocol and DNS probes all talk through a [Wire]:
+//! writes and reads share one overall deadline, and replies are buffered in
+//! `pending` up to a size limit so a misbehaving server cannot grow it.
+
+use crate::script::describe;
+use socket2::Socket;
+use std::{
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+/// Conversation on `io` (the socket itself or a TLS stream over it).
+pub(crate) struct Wire<'a, S> {
+    io: &'a mut S,
+    socket: &'a Socket,
+    /// Bytes received but not consumed yet.
+    pub(crate) pending: Vec<u8>,
+    start: Instant,
+    deadline: Instant,
+    /// Longest line or message accepted.
+    limit: usize,
+    /// Error for a stream that ends before the reply does.
+    closed: &'static str,
+    first_byte_ms: Option<f64>,
+}
+
+impl<'a, S: Read + Write> Wire<'a, S> {
+    /// Start a conversation that must finish within `timeout`; lines and
+    /// messages longer than `limit` bytes are errors.
+    pub(crate) fn new(io: &'a mut S, socket: &'a Socket, timeout: Duration, limit: usize) -> Self {
+        let start = Instant::now();
+        Self {
+            io,
+            socket,
+            pending: Vec::new(),
+            start,
+            deadline: start + timeout,
+            limit,
+            closed: "connection closed by server",
+            first_byte_ms: None,
+        }
+    }
+
+    /// Report a stream ending early with `message` instead of the default.
+    pub(crate) fn closed_with(mut self, message: &'static str) -> Self {
+        self.closed = message;
+        self
+    }
+
+    pub(crate) fn send(&mut self, data: &[u8]) -> Result<(), String> {
+        self.socket
+            .set_write_timeout(Some(self.remaining()?))
+            .and_then(|()| self.io.write_all(data))
+            .and_then(|()| self.io.flush())
+            .map_err(|err| describe(&err))
+    }
+
+    /// Next line without its line break.
+    pub(crate) fn line(&mut self) -> Result<String, String> {
+        loop {
+            if let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=end).collect();
+                return Ok(String::from_utf8_lossy(line.trim_ascii_end()).into_owned());
+            }
+            if self.pending.len() > self.limit {
+                return Err("reply line too long".into());
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Exactly `len` bytes, without reading past them, so whatever follows
+    /// stays on the stream.
+    pub(crate) fn exact(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        if len > self.limit {
+            return Err("reply too long".into());
+        }
+        while self.pending.len() < len {
+            if self.read_up_to(len - self.pending.len())? == 0 {
+                return Err(self.closed.into());
+            }
+        }
+        Ok(self.pending.drain(..len).collect())
+    }
+
+    /// Read more bytes into `pending`; the stream ending is an error.
+    pub(crate) fn fill(&mut self) -> Result<(), String> {
+        match self.read_more()? {
+            0 => Err(self.closed.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Read more bytes into `pending`; 0 at end of stream.
+    pub(crate) fn read_more(&mut self) -> Result<usize, String> {
+        self.read_up_to(4096)
+    }
+
+    /// Read at most `max` more bytes into `pending`; 0 at end of stream.
+    fn read_up_to(&mut self, max: usize) -> Result<usize, String> {
+        let mut buf = [0; 4096];
+        let buf = &mut buf[..max.min(4096)];
+        loop {
+            self.socket
+                .set_read_timeout(Some(self.remaining()?))
+                .map_err(|err| describe(&err))?;
+            match self.io.read(buf) {
+                Ok(n) => {
+                    if n > 0 && self.first_byte_ms.is_none() {
+                        self.first_byte_ms = Some(self.elapsed_ms());
+                    }
+                    self.pending.extend_from_slice(&buf[..n]);
+                    return Ok(n);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                // TLS peers that close without close_notify.
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(0),
+                Err(err) => return Err(describe(&err)),
+            }
+        }
+    }
+
+    /// Time from the start of the conversation to the first byte received.
+    pub(crate) fn first_byte_ms(&self) -> Option<f64> {
+        self.first_byte_ms
+    }
+
+    pub(crate) fn elapsed_ms(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1_000.0
+    }
+
+    fn remaining(&self) -> Result<Duration, String> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err("timed out".into());
+        }
+        Ok(remaining)
+    }
+}
@@ -0,0 +1,39 @@
+//! Helpers shared by the integration tests.
+
+use std::io::{Read, Write};
+
+/// Answer one gRPC health check on `stream` with `serving_status`, returning
+/// the request's header block.
+pub fn grpc_health_reply(stream: &mut (impl Read + Write), serving_status: u8) -> Vec<u8> {
+    let mut preface = [0; 24];
+    stream.read_exact(&mut preface).unwrap();
+    assert_eq!(&preface, b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+    let mut block = Vec::new();
+    loop {
+        let mut head = [0; 9];
+        stream.read_exact(&mut head).unwrap();
+        let len = usize::from(head[1]) << 8 | usize::from(head[2]);
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        match (head[3], head[4] & 1) {
+            (1, _) => block = payload,
+            (0, 1) => break,
+            _ => {}
+        }
+    }
+
+    let frame = |kind: u8, flags: u8, payload: &[u8]| {
+        let mut frame = vec![0, 0, payload.len() as u8, kind, flags, 0, 0, 0, 1];
+        frame.extend_from_slice(payload);
+        frame
+    };
+    let mut reply = vec![0, 0, 0, 4, 0, 0, 0, 0, 0];
+    reply.extend(frame(1, 4, b"\x88"));
+    reply.extend(frame(0, 0, &[0, 0, 0, 0, 2, 0x08, serving_status]));
+    reply.extend(frame(1, 5, b"\x00\x0bgrpc-status\x010"));
+    stream.write_all(&reply).unwrap();
+    // Wait for the SETTINGS acknowledgement before closing.
+    let mut ack = [0; 9];
+    stream.read_exact(&mut ack).unwrap();
+    block
+}
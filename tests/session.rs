@@ -1,10 +1,16 @@
+mod common;
+
+use common::grpc_health_reply;
 use std::{
-    io::ErrorKind,
+    io::{ErrorKind, Read, Write},
     net::TcpListener,
     thread,
     time::{Duration, Instant},
 };
-use tcping::{PingOptions, Target, run_collect_async};
+use tcping::{
+    CloseMode, PingOptions, ProbeOutcome, Protocol, ProtocolUrl, Proxy, ProxyKind, ProxyProtocol,
+    Target, parse_script, run_collect_async,
+};
 
 #[tokio::test]
 async fn collect_returns_probe_results_and_summary() {
@@ -44,3 +50,459 @@ async fn collect_returns_probe_results_and_summary() {
     assert_eq!(session.summary.exit_code(), 0);
     assert_eq!(acceptor.join().unwrap(), 2);
 }
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn probes_are_sent_from_the_requested_source() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = thread::spawn(move || listener.accept().map(|(_stream, peer)| peer).unwrap());
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(1)
+        .unwrap()
+        .with_timeout(Duration::from_millis(500))
+        .with_source_addr("127.0.0.2".parse().unwrap());
+
+    let session = run_collect_async(options).await.unwrap();
+    let peer = acceptor.join().unwrap();
+
+    assert_eq!(session.summary.successful_pings, 1);
+    assert_eq!(peer.ip().to_string(), "127.0.0.2");
+    assert_eq!(session.probes[0].local_addr, Some(peer));
+}
+
+#[tokio::test]
+async fn unusable_source_address_is_an_error() {
+    let target = Target::parse("127.0.0.1:9").unwrap();
+    let options = PingOptions::new(target)
+        .with_count(1)
+        .unwrap()
+        .with_source_addr("192.0.2.1".parse().unwrap());
+
+    assert!(run_collect_async(options).await.is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn successful_probes_carry_kernel_tcp_info() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = thread::spawn(move || listener.accept().map(|_| ()).unwrap());
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(1)
+        .unwrap()
+        .with_timeout(Duration::from_millis(500));
+
+    let session = run_collect_async(options).await.unwrap();
+    acceptor.join().unwrap();
+
+    let info = session.probes[0].tcp_info.expect("TCP_INFO on Linux");
+    assert!(info.snd_mss > 0);
+    assert_eq!(info.retransmits, 0);
+    assert!(!session.probes[0].retransmitted);
+    assert_eq!(session.summary.tcp_info.unwrap().samples, 1);
+}
+
+/// TIME_WAIT sockets (state `06`) in `/proc/net/tcp` with `port` on either end.
+#[cfg(target_os = "linux")]
+fn time_wait_entries(port: u16) -> usize {
+    let port = format!(":{port:04X}");
+    std::fs::read_to_string("/proc/net/tcp")
+        .unwrap()
+        .lines()
+        .skip(1)
+        .filter(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            fields[3] == "06" && (fields[1].ends_with(&port) || fields[2].ends_with(&port))
+        })
+        .count()
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn rst_close_leaves_no_time_wait() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = thread::spawn(move || {
+        for _ in 0..5 {
+            // Keep the server side open until the client's RST arrives.
+            let (stream, _) = listener.accept().unwrap();
+            let _ = std::io::Read::read(&mut &stream, &mut [0; 1]);
+        }
+    });
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(5)
+        .unwrap()
+        .with_interval(Duration::from_millis(1))
+        .with_timeout(Duration::from_millis(500))
+        .with_close_mode(CloseMode::Rst);
+
+    let session = run_collect_async(options).await.unwrap();
+    acceptor.join().unwrap();
+
+    assert_eq!(session.summary.successful_pings, 5);
+    assert_eq!(session.summary.close_mode, CloseMode::Rst);
+    assert_eq!(session.probes[0].close_mode, Some(CloseMode::Rst));
+    assert_eq!(time_wait_entries(addr.port()), 0);
+}
+
+#[tokio::test]
+async fn banner_mismatch_fails_the_probe() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            std::io::Write::write_all(&mut stream, b"220 smtp.example ESMTP\r\n").unwrap();
+        }
+    });
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(2)
+        .unwrap()
+        .with_interval(Duration::from_millis(1))
+        .with_timeout(Duration::from_millis(500))
+        .expect_banner("^SSH-")
+        .unwrap();
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert!(!probe.success);
+    assert_eq!(probe.outcome, ProbeOutcome::BannerMismatch);
+    assert_eq!(probe.banner.as_deref(), Some("220 smtp.example ESMTP"));
+    assert!(probe.time_to_first_byte_ms.is_some());
+    assert_eq!(session.summary.successful_pings, 0);
+}
+
+#[tokio::test]
+async fn send_expect_steps_are_timed() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 6];
+        std::io::Read::read_exact(&mut stream, &mut buf).unwrap();
+        std::io::Write::write_all(&mut stream, b"+PONG\r\n").unwrap();
+    });
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let steps = parse_script("send \"PING\\r\\n\"\nexpect \"+PONG\" timeout=500ms").unwrap();
+    let options = PingOptions::new(target)
+        .with_count(1)
+        .unwrap()
+        .with_timeout(Duration::from_millis(500))
+        .with_steps(steps);
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert!(probe.success);
+    assert_eq!(probe.outcome, ProbeOutcome::Open);
+    assert_eq!(probe.steps.len(), 2);
+    assert!(probe.steps.iter().all(|step| step.success));
+    assert_eq!(probe.steps[1].received.as_deref(), Some("+PONG"));
+}
+
+#[tokio::test]
+async fn http_status_decides_the_outcome() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in [
+            &b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"[..],
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+        ] {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = std::io::Read::read(&mut stream, &mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            std::io::Write::write_all(&mut stream, response).unwrap();
+            requests.push(String::from_utf8(request).unwrap());
+        }
+        requests
+    });
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(2)
+        .unwrap()
+        .with_interval(Duration::from_millis(1))
+        .with_timeout(Duration::from_millis(500))
+        .http_request("/healthz")
+        .unwrap()
+        .with_http_header("X-Probe", "1")
+        .unwrap();
+
+    let session = run_collect_async(options).await.unwrap();
+    let requests = server.join().unwrap();
+
+    assert!(requests[0].starts_with("GET /healthz HTTP/1.1\r\n"));
+    assert!(requests[0].contains(&format!("\r\nHost: 127.0.0.1:{}\r\n", addr.port())));
+    assert!(requests[0].contains("\r\nX-Probe: 1\r\n"));
+
+    let ok = session.probes[0].http.as_ref().expect("http info");
+    assert_eq!(session.probes[0].outcome, ProbeOutcome::Open);
+    assert_eq!((ok.status, ok.body_bytes), (Some(200), 2));
+    assert!(ok.ttfb_ms.is_some_and(|ttfb| ttfb <= ok.total_ms));
+
+    assert_eq!(session.probes[1].outcome, ProbeOutcome::HttpStatusMismatch);
+    assert_eq!(session.probes[1].http.as_ref().unwrap().status, Some(404));
+    assert_eq!(session.summary.successful_pings, 1);
+}
+
+#[tokio::test]
+async fn grpc_health_over_h2c() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        grpc_health_reply(&mut stream, 2)
+    });
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(1)
+        .unwrap()
+        .with_timeout(Duration::from_millis(500))
+        .grpc_health("billing");
+
+    let session = run_collect_async(options).await.unwrap();
+    let block = server.join().unwrap();
+
+    let authority = format!("127.0.0.1:{}", addr.port());
+    assert!(
+        block
+            .windows(authority.len())
+            .any(|w| w == authority.as_bytes())
+    );
+    let probe = &session.probes[0];
+    assert_eq!(probe.outcome, ProbeOutcome::GrpcNotServing);
+    let grpc = probe.grpc.as_ref().expect("grpc info");
+    assert_eq!(grpc.serving_status.as_deref(), Some("NOT_SERVING"));
+    assert_eq!(grpc.grpc_status, Some(0));
+    assert_eq!(session.summary.exit_code(), 1);
+}
+
+#[tokio::test]
+async fn redis_ping_reports_application_rtt() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        for reply in [&b"+OK\r\n+PONG\r\n"[..], b"-WRONGPASS invalid password\r\n"] {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"PING\r\n") && !request.ends_with(b"secret\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(reply).unwrap();
+            // Wait for the client to hang up before accepting the next probe.
+            let _ = stream.read(&mut buf);
+        }
+    });
+
+    let url = format!("redis://:secret@127.0.0.1:{}", addr.port());
+    let url = ProtocolUrl::parse(&url).unwrap().unwrap();
+    let options = PingOptions::new(url.target)
+        .with_count(2)
+        .unwrap()
+        .with_interval(Duration::from_millis(1))
+        .with_timeout(Duration::from_millis(500))
+        .with_protocol(url.protocol)
+        .with_protocol_credentials(url.username, url.password.unwrap())
+        .unwrap();
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let ok = session.probes[0].protocol.as_ref().expect("protocol info");
+    assert_eq!(session.probes[0].outcome, ProbeOutcome::Open);
+    assert_eq!((ok.protocol, ok.error.as_deref()), (Protocol::Redis, None));
+    assert!(ok.rtt_ms > 0.0);
+
+    let failed = session.probes[1].protocol.as_ref().expect("protocol info");
+    assert_eq!(session.probes[1].outcome, ProbeOutcome::ProtocolError);
+    assert_eq!(
+        failed.error.as_deref(),
+        Some("AUTH failed: WRONGPASS invalid password")
+    );
+    assert_eq!(session.summary.exit_code(), 1);
+}
+
+/// Answer one framed DNS query with `rcode` and a single A record.
+fn dns_stub_reply(stream: &mut std::net::TcpStream, rcode: u8) -> Vec<u8> {
+    let mut len = [0; 2];
+    stream.read_exact(&mut len).unwrap();
+    let mut query = vec![0; usize::from(u16::from_be_bytes(len))];
+    stream.read_exact(&mut query).unwrap();
+
+    let mut response = query.clone();
+    response[2] = 0x81;
+    response[3] = 0x80 | rcode;
+    if rcode == 0 {
+        response[7] = 1;
+        response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 192, 0, 2, 1]);
+    }
+    stream
+        .write_all(&(response.len() as u16).to_be_bytes())
+        .unwrap();
+    stream.write_all(&response).unwrap();
+    query
+}
+
+#[tokio::test]
+async fn dns_query_checks_the_response_code() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let mut queries = Vec::new();
+        for rcode in [0, 2] {
+            let (mut stream, _) = listener.accept().unwrap();
+            queries.push(dns_stub_reply(&mut stream, rcode));
+        }
+        queries
+    });
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(2)
+        .unwrap()
+        .with_interval(Duration::from_millis(1))
+        .with_timeout(Duration::from_millis(500))
+        .dns_query("example.com", "A")
+        .unwrap();
+
+    let session = run_collect_async(options).await.unwrap();
+    let queries = server.join().unwrap();
+    assert!(queries[0].ends_with(b"\x07example\x03com\x00\x00\x01\x00\x01"));
+
+    let ok = session.probes[0].dns.as_ref().expect("dns info");
+    assert_eq!(session.probes[0].outcome, ProbeOutcome::Open);
+    assert_eq!((ok.rcode.as_deref(), ok.answers), (Some("NOERROR"), 1));
+    assert!(ok.query_ms > 0.0);
+
+    let failed = session.probes[1].dns.as_ref().expect("dns info");
+    assert_eq!(session.probes[1].outcome, ProbeOutcome::DnsRcodeMismatch);
+    assert_eq!(failed.rcode.as_deref(), Some("SERVFAIL"));
+}
+
+#[tokio::test]
+async fn proxy_header_precedes_the_banner() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, peer) = listener.accept().unwrap();
+        let mut header = Vec::new();
+        let mut byte = [0; 1];
+        while !header.ends_with(b"\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            header.push(byte[0]);
+        }
+        stream.write_all(b"220 ready\r\n").unwrap();
+        (String::from_utf8(header).unwrap(), peer)
+    });
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(1)
+        .unwrap()
+        .with_timeout(Duration::from_millis(500))
+        .with_proxy_protocol(ProxyProtocol::V1)
+        .with_proxy_source("203.0.113.7:40000".parse().unwrap())
+        .unwrap()
+        .read_banner(true);
+
+    let session = run_collect_async(options).await.unwrap();
+    let (header, _) = server.join().unwrap();
+    assert_eq!(
+        header,
+        format!("PROXY TCP4 203.0.113.7 127.0.0.1 40000 {}\r\n", addr.port())
+    );
+    assert_eq!(session.probes[0].outcome, ProbeOutcome::Open);
+    assert_eq!(session.probes[0].banner.as_deref(), Some("220 ready"));
+}
+
+#[tokio::test]
+async fn socks5_proxy_tunnels_to_the_target_by_name() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut greeting = [0; 3];
+        stream.read_exact(&mut greeting).unwrap();
+        stream.write_all(&[5, 0]).unwrap();
+        let mut request = [0; 5 + 13 + 2];
+        stream.read_exact(&mut request).unwrap();
+        stream
+            .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 25])
+            .unwrap();
+        stream.write_all(b"220 ready\r\n").unwrap();
+        request
+    });
+
+    let proxy = Proxy::parse(&format!("socks5h://{addr}")).unwrap();
+    let options = PingOptions::new(Target::new("mail.internal", 25).unwrap())
+        .with_count(1)
+        .unwrap()
+        .with_timeout(Duration::from_millis(500))
+        .with_proxy(proxy)
+        .read_banner(true);
+
+    let session = run_collect_async(options).await.unwrap();
+    let request = server.join().unwrap();
+    assert_eq!(&request[..5], [5, 1, 0, 3, 13]);
+    assert_eq!(&request[5..18], b"mail.internal");
+    assert_eq!(session.resolved.addr, addr);
+    let probe = &session.probes[0];
+    assert_eq!(probe.outcome, ProbeOutcome::Open);
+    assert_eq!(probe.banner.as_deref(), Some("220 ready"));
+    let proxy = probe.proxy.as_ref().expect("proxy info");
+    assert_eq!(proxy.kind, ProxyKind::Socks5h);
+    assert_eq!(proxy.target, "mail.internal:25");
+}
+
+#[tokio::test]
+async fn busy_sweep_port_fails_only_its_own_probes() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let busy = TcpListener::bind("127.0.0.1:0").unwrap();
+    let busy_port = busy.local_addr().unwrap().port();
+    let free_port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(2)
+        .unwrap()
+        .with_interval(Duration::from_millis(10))
+        .with_timeout(Duration::from_millis(500))
+        .with_source_addr(addr.ip())
+        .with_source_port_sweep([busy_port, free_port]);
+
+    let session = run_collect_async(options).await.unwrap();
+    assert_eq!(session.probes[0].outcome, ProbeOutcome::SourcePortBusy);
+    assert_eq!(session.probes[1].outcome, ProbeOutcome::Open);
+    let ports = &session.summary.source_ports;
+    assert_eq!(ports.len(), 2);
+    let busy_stats = ports.iter().find(|p| p.source_port == busy_port).unwrap();
+    assert_eq!(busy_stats.packet_loss, 100.0);
+    drop(busy);
+}
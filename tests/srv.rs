@@ -0,0 +1,76 @@
+use std::{net::UdpSocket, thread};
+use tcping::resolve_srv_with;
+
+/// Answer one SRV query with the given `(priority, weight, port, host)` records.
+fn spawn_stub_dns(records: Vec<(u16, u16, u16, &'static str)>) -> std::net::SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        let (len, peer) = socket.recv_from(&mut buf).unwrap();
+        let query = &buf[..len];
+
+        let mut response = query.to_vec();
+        response[2] = 0x81;
+        response[3] = 0x80;
+        response[6..8].copy_from_slice(&(records.len() as u16).to_be_bytes());
+
+        for (priority, weight, port, host) in records {
+            let mut rdata = Vec::new();
+            rdata.extend_from_slice(&priority.to_be_bytes());
+            rdata.extend_from_slice(&weight.to_be_bytes());
+            rdata.extend_from_slice(&port.to_be_bytes());
+            for label in host.split('.').filter(|label| !label.is_empty()) {
+                rdata.push(label.len() as u8);
+                rdata.extend_from_slice(label.as_bytes());
+            }
+            rdata.push(0);
+
+            response.extend_from_slice(&[0xc0, 0x0c, 0, 33, 0, 1, 0, 0, 0, 60]);
+            response.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            response.extend_from_slice(&rdata);
+        }
+
+        // A reply to some other query arrives first and must be ignored.
+        let mut stray = response.clone();
+        stray[0] ^= 0xff;
+        socket.send_to(&stray, peer).unwrap();
+        socket.send_to(&response, peer).unwrap();
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn srv_records_are_ordered_by_priority() {
+    let server = spawn_stub_dns(vec![
+        (20, 0, 636, "backup.example.com"),
+        (10, 5, 389, "ldap1.example.com"),
+        (10, 5, 389, "ldap2.example.com"),
+    ]);
+
+    let records = resolve_srv_with("_ldap._tcp.example.com", server)
+        .await
+        .unwrap();
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].priority, 10);
+    assert_eq!(records[1].priority, 10);
+    assert_eq!(records[2].host, "backup.example.com");
+    assert_eq!(records[2].port, 636);
+
+    let target = records[2].target().unwrap();
+    assert_eq!(target.to_string(), "backup.example.com:636");
+}
+
+#[tokio::test]
+async fn srv_root_target_means_service_unavailable() {
+    let server = spawn_stub_dns(vec![(0, 0, 0, "")]);
+
+    let err = resolve_srv_with("_ldap._tcp.example.com", server)
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("unavailable"));
+}
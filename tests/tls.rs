@@ -0,0 +1,484 @@
+#![cfg(feature = "tls")]
+
+mod common;
+
+use common::grpc_health_reply;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+use tcping::{PingOptions, PingResult, ProbeOutcome, StartTls, Target, run_collect_async};
+
+/// Serve `connections` TLS handshakes with `config`.
+fn serve(
+    config: rustls::ServerConfig,
+    connections: usize,
+) -> (std::net::SocketAddr, thread::JoinHandle<()>) {
+    serve_after(config, connections, |_| {})
+}
+
+/// Like [serve], running the plaintext `prelude` on each connection first.
+fn serve_after(
+    config: rustls::ServerConfig,
+    connections: usize,
+    prelude: impl Fn(&mut TcpStream) + Send + 'static,
+) -> (std::net::SocketAddr, thread::JoinHandle<()>) {
+    let config = Arc::new(config);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        for _ in 0..connections {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .unwrap();
+            prelude(&mut stream);
+            let mut conn = rustls::ServerConnection::new(config.clone()).unwrap();
+            while conn.is_handshaking() {
+                if conn.complete_io(&mut stream).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    (addr, server)
+}
+
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn self_signed_config() -> rustls::ServerConfig {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let chain = vec![CertificateDer::from(cert.cert.der().to_vec())];
+    let key = PrivateKeyDer::Pkcs8(cert.signing_key.serialize_der().into());
+
+    let mut config = rustls::ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .unwrap();
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    config
+}
+
+/// Serve `connections` TLS handshakes with a self-signed `localhost` certificate.
+fn tls_server(connections: usize) -> (std::net::SocketAddr, thread::JoinHandle<()>) {
+    serve(self_signed_config(), connections)
+}
+
+/// A private CA with a `localhost` server certificate and a client
+/// certificate, written out as PEM files.
+struct Pki {
+    dir: PathBuf,
+    ca: rcgen::Certificate,
+    server: (rcgen::Certificate, rcgen::KeyPair),
+}
+
+impl Pki {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("tcping-mtls-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut ca_params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "tcping test CA");
+        let ca_key = rcgen::KeyPair::generate().unwrap();
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let issuer = rcgen::Issuer::new(ca_params, ca_key);
+
+        let leaf = |san: &str, usage| {
+            let mut params = rcgen::CertificateParams::new(vec![san.to_string()]).unwrap();
+            params.extended_key_usages = vec![usage];
+            let key = rcgen::KeyPair::generate().unwrap();
+            (params.signed_by(&key, &issuer).unwrap(), key)
+        };
+        let server = leaf("localhost", rcgen::ExtendedKeyUsagePurpose::ServerAuth);
+        let (client, client_key) = leaf("client", rcgen::ExtendedKeyUsagePurpose::ClientAuth);
+
+        std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.join("client.pem"), client.pem()).unwrap();
+        std::fs::write(dir.join("client.key"), client_key.serialize_pem()).unwrap();
+        Self { dir, ca, server }
+    }
+
+    /// Server requiring a client certificate issued by the CA.
+    fn mtls_server(&self) -> (std::net::SocketAddr, thread::JoinHandle<()>) {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(self.ca.der().clone()).unwrap();
+        let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(
+            Arc::new(roots),
+            provider(),
+        )
+        .build()
+        .unwrap();
+
+        let (cert, key) = &self.server;
+        let config = rustls::ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                vec![cert.der().clone()],
+                PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+            )
+            .unwrap();
+        serve(config, 1)
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+}
+
+impl Drop for Pki {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn options(addr: std::net::SocketAddr) -> PingOptions {
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    PingOptions::new(target)
+        .with_count(1)
+        .unwrap()
+        .with_timeout(Duration::from_secs(2))
+        .tls(true)
+        .with_tls_server_name("localhost")
+}
+
+#[tokio::test]
+async fn tls_handshake_is_timed_separately() {
+    let (addr, server) = tls_server(1);
+    let options = options(addr).insecure_tls(true).with_alpn(["h2"]);
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert!(probe.success);
+    let tls = probe.tls.as_ref().expect("tls info");
+    assert_eq!(tls.version.as_deref(), Some("TLSv1.3"));
+    assert_eq!(tls.alpn.as_deref(), Some("h2"));
+    assert!(tls.cipher.is_some());
+    assert!(tls.handshake_ms > 0.0);
+    assert_eq!(tls.error, None);
+
+    let cert = session.summary.certificate.as_ref().expect("certificate");
+    assert_eq!(cert.sans, ["localhost"]);
+    assert_eq!(cert.chain_len, 1);
+    assert!(cert.name_matches);
+    assert!(cert.days_remaining > 365);
+    assert_eq!(session.summary.exit_code(), 0);
+}
+
+#[tokio::test]
+async fn https_request_runs_over_the_handshake() {
+    let mut config = self_signed_config();
+    config.alpn_protocols.push(b"http/1.1".to_vec());
+    let config = Arc::new(config);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let conn = rustls::ServerConnection::new(config).unwrap();
+        let mut tls = rustls::StreamOwned::new(conn, stream);
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            let n = tls.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        tls.write_all(b"HTTP/1.1 503 Service Unavailable\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nbusy\r\n0\r\n\r\n")
+            .unwrap();
+        tls.conn.send_close_notify();
+        tls.flush().unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    let target = Target::new(addr.ip().to_string(), addr.port()).unwrap();
+    let options = PingOptions::new(target)
+        .with_count(1)
+        .unwrap()
+        .with_timeout(Duration::from_secs(2))
+        .http_request("https://localhost/ready")
+        .unwrap()
+        .expect_status("2xx,503")
+        .unwrap()
+        .insecure_tls(true);
+
+    let session = run_collect_async(options).await.unwrap();
+    let request = server.join().unwrap();
+
+    assert!(request.starts_with("GET /ready HTTP/1.1\r\nHost: localhost\r\n"));
+    let probe = &session.probes[0];
+    assert_eq!(probe.outcome, ProbeOutcome::Open);
+    // HTTP probes offer http/1.1 so the server does not pick h2.
+    assert_eq!(
+        probe.tls.as_ref().unwrap().alpn.as_deref(),
+        Some("http/1.1")
+    );
+    let http = probe.http.as_ref().expect("http info");
+    assert_eq!((http.status, http.body_bytes), (Some(503), 4));
+    assert_eq!(
+        session.summary.certificate.as_ref().unwrap().sans,
+        ["localhost"]
+    );
+}
+
+#[tokio::test]
+async fn certificate_expiry_threshold_sets_the_exit_code() {
+    let (addr, server) = tls_server(1);
+    // rcgen's default validity runs to the year 4096.
+    let options = options(addr)
+        .insecure_tls(true)
+        .with_cert_warn_days(1_000_000);
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    assert_eq!(session.summary.successful_pings, 1);
+    assert!(session.summary.cert_expiring());
+    assert_eq!(session.summary.exit_code(), 1);
+}
+
+#[tokio::test]
+async fn untrusted_certificate_is_an_unknown_ca() {
+    let (addr, server) = tls_server(1);
+
+    let session = run_collect_async(options(addr)).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert!(!probe.success);
+    assert_eq!(probe.outcome, ProbeOutcome::TlsUnknownCa);
+    let error = probe.tls.as_ref().and_then(|tls| tls.error.as_deref());
+    assert!(
+        error.is_some_and(|error| error.contains("certificate")),
+        "{error:?}"
+    );
+    assert_eq!(session.summary.successful_pings, 0);
+    // The chain is still described when verification fails.
+    assert!(session.summary.certificate.is_some());
+}
+
+#[tokio::test]
+async fn mtls_handshake_with_client_certificate() {
+    let pki = Pki::new("ok");
+    let (addr, server) = pki.mtls_server();
+    let options = options(addr)
+        .with_ca_file(pki.path("ca.pem"))
+        .with_client_cert(pki.path("client.pem"), pki.path("client.key"));
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert_eq!(probe.outcome, ProbeOutcome::Open, "{:?}", probe.tls);
+    assert_eq!(session.summary.certificate.as_ref().unwrap().chain_len, 1);
+}
+
+#[tokio::test]
+async fn missing_client_certificate_is_rejected() {
+    let pki = Pki::new("rejected");
+    let (addr, server) = pki.mtls_server();
+    let options = options(addr).with_ca_file(pki.path("ca.pem"));
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert_eq!(probe.outcome, ProbeOutcome::TlsRejected, "{:?}", probe.tls);
+    assert!(!probe.success);
+}
+
+#[tokio::test]
+async fn unreadable_ca_file_fails_before_probing() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let options = options(listener.local_addr().unwrap()).with_ca_file("/nonexistent/ca.pem");
+
+    let err = run_collect_async(options).await.unwrap_err();
+    assert!(err.to_string().contains("/nonexistent/ca.pem"), "{err}");
+    assert_eq!(
+        listener.accept().unwrap_err().kind(),
+        std::io::ErrorKind::WouldBlock
+    );
+}
+
+#[tokio::test]
+async fn wrong_server_name_fails_verification() {
+    let pki = Pki::new("name");
+    let (addr, server) = pki.mtls_server();
+    let options = options(addr)
+        .with_ca_file(pki.path("ca.pem"))
+        .with_tls_server_name("other.test");
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert_eq!(
+        probe.outcome,
+        ProbeOutcome::TlsVerifyFailed,
+        "{:?}",
+        probe.tls
+    );
+    let cert = session.summary.certificate.as_ref().unwrap();
+    assert!(!cert.name_matches);
+}
+
+#[test]
+fn unreadable_client_key_is_an_invalid_option() {
+    let target = Target::new("127.0.0.1", 443).unwrap();
+    let options = PingOptions::new(target).with_client_cert("/nonexistent.pem", "/nonexistent.key");
+    let err = tcping::run_collect(options).unwrap_err();
+    assert!(err.to_string().contains("/nonexistent.pem"), "{err}");
+}
+
+/// Read one CRLF-terminated line byte by byte, leaving the TLS records that
+/// follow in the socket.
+fn read_line(stream: &mut TcpStream) -> String {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    while !line.ends_with(b"\r\n") {
+        stream.read_exact(&mut byte).unwrap();
+        line.push(byte[0]);
+    }
+    String::from_utf8(line).unwrap()
+}
+
+/// Probe a self-signed TLS server behind a scripted plaintext `prelude`.
+async fn starttls_probe(
+    protocol: StartTls,
+    prelude: impl Fn(&mut TcpStream) + Send + 'static,
+) -> PingResult {
+    let (addr, server) = serve_after(self_signed_config(), 1, prelude);
+    let options = options(addr).insecure_tls(true).with_starttls(protocol);
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+    session.probes.into_iter().next().unwrap()
+}
+
+fn assert_upgraded(probe: &PingResult) {
+    assert_eq!(probe.outcome, ProbeOutcome::Open, "{:?}", probe.tls);
+    let tls = probe.tls.as_ref().unwrap();
+    assert!(tls.starttls_ms.is_some_and(|ms| ms > 0.0));
+    assert!(tls.handshake_ms > 0.0);
+    assert_eq!(tls.version.as_deref(), Some("TLSv1.3"));
+}
+
+#[tokio::test]
+async fn smtp_starttls_upgrade() {
+    let probe = starttls_probe(StartTls::Smtp, |stream| {
+        stream.write_all(b"220 mail.test ESMTP\r\n").unwrap();
+        assert_eq!(read_line(stream), "EHLO tcping\r\n");
+        stream
+            .write_all(b"250-mail.test\r\n250-PIPELINING\r\n250 STARTTLS\r\n")
+            .unwrap();
+        assert_eq!(read_line(stream), "STARTTLS\r\n");
+        stream
+            .write_all(b"220 2.0.0 Ready to start TLS\r\n")
+            .unwrap();
+    })
+    .await;
+    assert_upgraded(&probe);
+}
+
+#[tokio::test]
+async fn imap_starttls_upgrade() {
+    let probe = starttls_probe(StartTls::Imap, |stream| {
+        stream
+            .write_all(b"* OK [CAPABILITY IMAP4rev1 STARTTLS] ready\r\n")
+            .unwrap();
+        assert_eq!(read_line(stream), "a1 STARTTLS\r\n");
+        stream
+            .write_all(b"a1 OK Begin TLS negotiation now\r\n")
+            .unwrap();
+    })
+    .await;
+    assert_upgraded(&probe);
+}
+
+#[tokio::test]
+async fn pop3_starttls_upgrade() {
+    let probe = starttls_probe(StartTls::Pop3, |stream| {
+        stream.write_all(b"+OK POP3 ready\r\n").unwrap();
+        assert_eq!(read_line(stream), "STLS\r\n");
+        stream.write_all(b"+OK Begin TLS\r\n").unwrap();
+    })
+    .await;
+    assert_upgraded(&probe);
+}
+
+#[tokio::test]
+async fn ldap_starttls_upgrade() {
+    let probe = starttls_probe(StartTls::Ldap, |stream| {
+        let mut request = [0; 31];
+        stream.read_exact(&mut request).unwrap();
+        assert!(request.ends_with(b"1.3.6.1.4.1.1466.20037"));
+        // messageID 1, ExtendedResponse { success, "", "" }
+        stream
+            .write_all(b"\x30\x0c\x02\x01\x01\x78\x07\x0a\x01\x00\x04\x00\x04\x00")
+            .unwrap();
+    })
+    .await;
+    assert_upgraded(&probe);
+}
+
+#[tokio::test]
+async fn refused_starttls_fails_the_probe() {
+    let (addr, server) = serve_after(self_signed_config(), 1, |stream| {
+        stream.write_all(b"+OK POP3 ready\r\n").unwrap();
+        read_line(stream);
+        stream.write_all(b"-ERR TLS not available\r\n").unwrap();
+    });
+    let options = options(addr)
+        .insecure_tls(true)
+        .with_starttls(StartTls::Pop3);
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert_eq!(probe.outcome, ProbeOutcome::StartTlsFailed);
+    let tls = probe.tls.as_ref().unwrap();
+    assert_eq!(
+        tls.error.as_deref(),
+        Some("server refused STLS: -ERR TLS not available")
+    );
+}
+
+#[tokio::test]
+async fn grpc_health_over_tls_negotiates_h2() {
+    let config = Arc::new(self_signed_config());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let conn = rustls::ServerConnection::new(config).unwrap();
+        let mut tls = rustls::StreamOwned::new(conn, stream);
+        grpc_health_reply(&mut tls, 1);
+    });
+
+    let options = options(addr).insecure_tls(true).grpc_health("");
+
+    let session = run_collect_async(options).await.unwrap();
+    server.join().unwrap();
+
+    let probe = &session.probes[0];
+    assert_eq!(probe.outcome, ProbeOutcome::Open);
+    assert_eq!(probe.tls.as_ref().unwrap().alpn.as_deref(), Some("h2"));
+    let grpc = probe.grpc.as_ref().expect("grpc info");
+    assert_eq!(grpc.serving_status.as_deref(), Some("SERVING"));
+    assert!(grpc.rpc_ms > 0.0);
+}